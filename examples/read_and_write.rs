@@ -3,8 +3,8 @@ use std::thread;
 use streamdeck_hid_rs::StreamDeckDevice;
 
 fn main() {
-    let mut hidapi = hidapi::HidApi::new().unwrap();
-    let device = Arc::new(StreamDeckDevice::open_first_device(&mut hidapi).unwrap());
+    let hidapi = hidapi::HidApi::new().unwrap();
+    let device = Arc::new(StreamDeckDevice::open_first_device(&hidapi).unwrap());
     let image = image::RgbImage::new(
         device.device_type.button_image_size().0,
         device.device_type.button_image_size().1,
@@ -1,8 +1,8 @@
 use streamdeck_hid_rs::StreamDeckDevice;
 
 fn main() {
-    let mut hidapi = hidapi::HidApi::new().unwrap();
-    let device = StreamDeckDevice::open_first_device(&mut hidapi).unwrap();
+    let hidapi = hidapi::HidApi::new().unwrap();
+    let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
     let image = image::RgbImage::new(
         device.device_type.button_image_size().0,
         device.device_type.button_image_size().1,
@@ -0,0 +1,127 @@
+//! Call a separate closure for every button and edge.
+//!
+//! Instead of one callback for all button events, closures are registered for a
+//! button going down or up, and only the matching closures are called.
+
+use crate::hid_api_traits::*;
+use crate::{ButtonState, Error, StreamDeckDevice};
+use std::cell::RefCell;
+
+/// A closure registered with [ButtonDispatcher::on].
+type Handler<'a> = (u32, ButtonState, Box<dyn FnMut() + 'a>);
+
+/// Dispatches the button events of a device to closures registered per button and edge.
+///
+/// This is a lighter alternative to writing a match over the events in the closure of
+/// [StreamDeckDevice::on_button_events]. The dispatcher borrows the device, so the
+/// closures can borrow it as well, e.g. to change the image of the pressed button.
+///
+/// # Example
+/// ```
+/// use streamdeck_hid_rs::{ButtonDispatcher, ButtonState, StreamDeckDevice};
+///
+/// fn main() {
+///     let hidapi = hidapi::HidApi::new().unwrap();
+///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+///     let mut dispatcher = ButtonDispatcher::new(&device);
+///     dispatcher
+///         .on(0, ButtonState::Down, || println!("Play"))
+///         .on(1, ButtonState::Down, || println!("Pause"));
+///     dispatcher.pump_once().unwrap();
+/// }
+/// ```
+pub struct ButtonDispatcher<'a, API: HidApiTrait> {
+    device: &'a StreamDeckDevice<API>,
+    handlers: RefCell<Vec<Handler<'a>>>,
+}
+
+impl<'a, API: HidApiTrait> ButtonDispatcher<'a, API> {
+    /// Create a dispatcher for the events of a device, without any closures.
+    pub fn new(device: &'a StreamDeckDevice<API>) -> ButtonDispatcher<'a, API> {
+        ButtonDispatcher {
+            device,
+            handlers: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Register a closure, called when `button_id` changes to `state`.
+    ///
+    /// Several closures can be registered for the same button and edge, they are
+    /// called in the order of registration.
+    pub fn on<F>(&mut self, button_id: u32, state: ButtonState, handler: F) -> &mut Self
+    where
+        F: FnMut() + 'a,
+    {
+        self.handlers
+            .get_mut()
+            .push((button_id, state, Box::new(handler)));
+        self
+    }
+
+    /// Read one report from the device and call the closures matching its events.
+    ///
+    /// This blocks until the device sends a report, see
+    /// [StreamDeckDevice::read_button_events]. Call it in a loop, to keep dispatching.
+    pub fn pump_once(&self) -> Result<(), Error> {
+        let events = self.device.read_button_events()?;
+        let mut handlers = self.handlers.borrow_mut();
+        for event in events {
+            for (button_id, state, handler) in handlers.iter_mut() {
+                if *button_id == event.button_id && *state == event.state {
+                    handler();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamDeckType;
+    use mockall::Sequence;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_pump_once() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        for pressed in [true, false] {
+            hid_device
+                .expect_read()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |data: &mut [u8]| {
+                    data.fill(0);
+                    data[StreamDeckType::Mini.button_read_offset() + 2] = pressed as u8;
+                    Ok(data.len())
+                });
+        }
+        let device =
+            StreamDeckDevice::<MockMockHidApi>::from_hid_device(hid_device, StreamDeckType::Mini);
+        let down = Cell::new(0);
+        let up = Cell::new(0);
+        let other = Cell::new(0);
+        let mut dispatcher = ButtonDispatcher::new(&device);
+        dispatcher
+            .on(2, ButtonState::Down, || down.set(down.get() + 1))
+            .on(2, ButtonState::Up, || up.set(up.get() + 1))
+            .on(3, ButtonState::Down, || other.set(other.get() + 1));
+
+        // Act
+        dispatcher.pump_once().unwrap();
+        let down_after_press = down.get();
+        let up_after_press = up.get();
+        dispatcher.pump_once().unwrap();
+
+        // Test
+        assert_eq!(down_after_press, 1);
+        assert_eq!(up_after_press, 0);
+        assert_eq!(up.get(), 1);
+        assert_eq!(down.get(), 1);
+        assert_eq!(other.get(), 0);
+    }
+}
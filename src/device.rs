@@ -1,34 +1,441 @@
 use crate::hid_api_traits::*;
-use crate::image::image_packages;
+use crate::image::{
+    brighten_image, button_packages, dim_image, encode_button_image, fast_image_hash,
+    image_packages_into, image_packages_iter, info_image_packages, touchscreen_image_packages,
+    CacheHashMode, DimensionPolicy, ImageOptions, TestPattern,
+};
 use crate::Error;
-use crate::StreamDeckType;
-use image::RgbImage;
-use log::debug;
+use crate::{DeviceCapabilities, StreamDeckImageFormat, StreamDeckType, UploadGovernor};
+use image::{imageops, Rgb, RgbImage};
+use log::{debug, info, warn};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// The state a button can be in or change to.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ButtonState {
     Down,
     Up,
 }
 
 /// Event send, when a button changes its state!
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct ButtonEvent {
     pub button_id: u32,
     pub state: ButtonState,
 }
 
+/// Event send, when an encoder (dial) of the Streamdeck Plus is turned or pressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncoderEvent {
+    /// The encoder has been turned by `delta` steps, clockwise positive.
+    Turn { encoder: u32, delta: i32 },
+    /// The encoder has been pressed or released.
+    Press { encoder: u32, state: ButtonState },
+}
+
+/// Event send, when the touchscreen of the Streamdeck Plus is touched.
+///
+/// Coordinates are in pixels of the touchscreen, see [StreamDeckType::touchscreen_size].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TouchEvent {
+    /// A short tap.
+    Short { x: u16, y: u16 },
+    /// A long press.
+    Long { x: u16, y: u16 },
+    /// A swipe from `(x, y)` to `(x_out, y_out)`.
+    Drag {
+        x: u16,
+        y: u16,
+        x_out: u16,
+        y_out: u16,
+    },
+}
+
+/// Any input of a Streamdeck, see [StreamDeckDevice::on_input_events].
+#[derive(Debug, Clone, Copy)]
+pub enum InputEvent {
+    Button(ButtonEvent),
+    Encoder(EncoderEvent),
+    Touch(TouchEvent),
+}
+
+/// Event send, when a button changes its state, with the position of the button.
+#[derive(Debug, Clone)]
+pub struct GridButtonEvent {
+    /// The row of the button, starting at the top.
+    pub row: u32,
+    /// The column of the button, starting at the left.
+    pub col: u32,
+    pub state: ButtonState,
+}
+
+/// How [StreamDeckDevice::transition_to] changes the images on the buttons.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TransitionStyle {
+    /// The buttons are changed column by column, starting at the left.
+    LeftToRight,
+    /// The brightness is faded out, all buttons are changed and the brightness is faded in.
+    Fade,
+    /// The buttons are changed in a random order.
+    Random,
+}
+
+/// How the Streamdeck is mounted.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Rotation {
+    /// The Streamdeck is mounted normally.
+    None,
+    /// The Streamdeck is mounted upside down (rotated by 180°).
+    Upside180,
+}
+
+impl Rotation {
+    /// Rotate an image from user orientation to the orientation of the panel.
+    fn apply<'a>(&self, image: &'a RgbImage) -> Cow<'a, RgbImage> {
+        match self {
+            Rotation::None => Cow::Borrowed(image),
+            Rotation::Upside180 => Cow::Owned(imageops::rotate180(image)),
+        }
+    }
+}
+
+/// The kind of input a report of the Streamdeck Plus carries.
+///
+/// The Streamdeck Plus sends button, encoder and touch screen input on the
+/// same report id and distinguishes them by the second byte of the report.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PlusInputKind {
+    Buttons,
+    Touch,
+    Encoders,
+}
+
+impl PlusInputKind {
+    /// Classify an input report read from a Streamdeck Plus.
+    ///
+    /// Returns `None` if the report is too short or of an unknown kind.
+    pub fn classify(report: &[u8]) -> Option<PlusInputKind> {
+        match report.get(1)? {
+            0x00 => Some(PlusInputKind::Buttons),
+            0x02 => Some(PlusInputKind::Touch),
+            0x03 => Some(PlusInputKind::Encoders),
+            _ => None,
+        }
+    }
+}
+
+/// A Streamdeck found by [StreamDeckDevice::describe_devices], with its USB descriptor strings
+/// and the bus it is connected with.
+#[derive(Debug, Clone)]
+pub struct DeviceDescription<D> {
+    /// The type of the Streamdeck.
+    pub device_type: StreamDeckType,
+    /// The manufacturer string of the device, if available.
+    pub manufacturer: Option<String>,
+    /// The product string of the device, if available.
+    pub product: Option<String>,
+    /// The bus the device is connected with.
+    ///
+    /// hidapi does not report the USB speed, so slow connections can not be detected.
+    pub bus_type: hidapi::BusType,
+    /// The device info, to open the device with [StreamDeckDevice::open].
+    pub info: D,
+}
+
+/// Button images encoded ahead of time, see [StreamDeckDevice::prepare_animation].
+#[derive(Debug, Clone)]
+pub struct PreparedAnimation {
+    device_type: StreamDeckType,
+    /// The encoded frames, ready to be split into packages.
+    frames: Vec<Vec<u8>>,
+}
+
+impl PreparedAnimation {
+    /// The number of frames in the animation.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Timing information about uploading a button image.
+#[derive(Debug, Clone)]
+pub struct UploadStats {
+    /// Time spend encoding the image and splitting it into packets.
+    pub encode: Duration,
+    /// Time spend writing the packets to the device.
+    pub write: Duration,
+    /// Number of packets written to the device.
+    pub packets: usize,
+}
+
+/// Result of [StreamDeckDevice::benchmark_upload].
+#[derive(Debug, Clone)]
+pub struct UploadBenchmark {
+    /// Number of images uploaded.
+    pub iterations: usize,
+    /// Number of packets written to the device.
+    pub packets: usize,
+    /// Number of bytes written to the device, including headers and padding.
+    pub bytes: usize,
+    /// Time spend for all uploads, including encoding.
+    pub elapsed: Duration,
+    /// Bytes written per second, 0 if no time could be measured.
+    pub bytes_per_second: f64,
+    /// Packets written per second, 0 if no time could be measured.
+    pub packets_per_second: f64,
+}
+
+/// One step of [StreamDeckDevice::self_test] and its outcome.
+#[derive(Debug)]
+pub struct SelfTestStep {
+    /// What has been tried, e.g. "set image of button 3".
+    pub name: String,
+    /// The error, if the step failed.
+    pub result: Result<(), Error>,
+}
+
+/// The outcome of [StreamDeckDevice::self_test], with every step attempted.
+#[derive(Debug)]
+pub struct SelfTestReport {
+    /// The steps in the order they have been attempted.
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    /// Check if all steps succeeded.
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.result.is_ok())
+    }
+
+    /// The steps that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestStep> {
+        self.steps.iter().filter(|step| step.result.is_err())
+    }
+}
+
 pub struct StreamDeckDevice<API: HidApiTrait> {
     pub device_type: StreamDeckType,
     hid_device: API::HidDevice,
+    image_options: ImageOptions,
+    panel_rotation: Rotation,
+    /// The images last uploaded to the buttons, if known.
+    framebuffer: Mutex<Vec<Option<RgbImage>>>,
+    /// The [fast_image_hash] of the framebuffer images, kept in [CacheHashMode::Fast].
+    framebuffer_hashes: Mutex<Vec<Option<u64>>>,
+    /// The button states, as last read from the device.
+    button_state: Mutex<Vec<ButtonState>>,
+    /// Buttons pressed during [StreamDeckDevice::suppress_input], whose release is discarded.
+    suppressed_presses: Mutex<Vec<bool>>,
+    /// The brightness last set, if any.
+    brightness: Mutex<Option<u8>>,
+    /// The brightness before [StreamDeckDevice::power_off], to restore it.
+    brightness_before_off: Mutex<Option<u8>>,
+    /// The accumulated turns of the encoders of the Streamdeck Plus.
+    encoder_positions: Mutex<[i32; NUM_PLUS_ENCODERS]>,
+    /// Whether the encoders of the Streamdeck Plus are pressed.
+    encoder_states: Mutex<[ButtonState; NUM_PLUS_ENCODERS]>,
+    /// Log the packets instead of sending them to the device.
+    dry_run: bool,
+    /// Refuse writing to the device, see [StreamDeckDevice::open_input_only].
+    input_only: bool,
+    /// How the images in the framebuffer are compared with new images.
+    cache_hash_mode: CacheHashMode,
+    /// Encode the images in [StreamDeckDevice::set_all_button_images] in parallel.
+    #[cfg(feature = "rayon")]
+    parallel_encoding: bool,
+    /// The serial number, if known. Only used for logging.
+    serial_number: Option<String>,
+    /// Limits the concurrent image uploads, if set.
+    upload_governor: Option<Arc<UploadGovernor>>,
+    /// Pause between the packets of an image.
+    interpacket_delay: Duration,
+    /// Wrap errors from the hid device in [Error::Device].
+    error_context: bool,
+    /// The color of cleared buttons.
+    default_background: Rgb<u8>,
+    /// Brighten pressed buttons by this factor, see [StreamDeckDevice::set_press_flash].
+    press_flash: Option<f32>,
 }
 
 unsafe impl Sync for StreamDeckDevice<hidapi::HidApi> {}
 
 impl<API: HidApiTrait> StreamDeckDevice<API> {
+    /// Create the device object from an opened hid device.
+    fn new(device_type: StreamDeckType, hid_device: API::HidDevice) -> StreamDeckDevice<API> {
+        StreamDeckDevice {
+            framebuffer: Mutex::new(vec![None; device_type.total_num_buttons()]),
+            framebuffer_hashes: Mutex::new(vec![None; device_type.total_num_buttons()]),
+            button_state: Mutex::new(vec![ButtonState::Up; device_type.total_num_buttons()]),
+            suppressed_presses: Mutex::new(vec![false; device_type.total_num_buttons()]),
+            brightness: Mutex::new(None),
+            brightness_before_off: Mutex::new(None),
+            encoder_positions: Mutex::new([0; NUM_PLUS_ENCODERS]),
+            encoder_states: Mutex::new([ButtonState::Up; NUM_PLUS_ENCODERS]),
+            device_type,
+            hid_device,
+            image_options: ImageOptions::default(),
+            panel_rotation: Rotation::None,
+            dry_run: false,
+            input_only: false,
+            cache_hash_mode: CacheHashMode::Full,
+            #[cfg(feature = "rayon")]
+            parallel_encoding: false,
+            serial_number: None,
+            upload_governor: None,
+            interpacket_delay: Duration::ZERO,
+            error_context: false,
+            default_background: Rgb([0, 0, 0]),
+            press_flash: None,
+        }
+    }
+
+    /// Open the device of a device info by its path.
+    ///
+    /// Opening by path (and not by vendor and product id) makes sure, that the serial
+    /// number taken from `device_info` belongs to the opened device, also with several
+    /// devices of the same type.
+    fn open_path(
+        api: &API,
+        device_type: StreamDeckType,
+        device_info: &API::DeviceInfo,
+    ) -> Result<StreamDeckDevice<API>, Error> {
+        let hid_device = api
+            .open_path(&device_info.path())
+            .map_err(Error::HidError)?;
+        let mut device = StreamDeckDevice::new(device_type, hid_device);
+        device.serial_number = device_info.serial_number();
+        info!("Opened {}", device.log_context());
+        Ok(device)
+    }
+
+    /// Description of the device for log messages.
+    fn log_context(&self) -> String {
+        match &self.serial_number {
+            Some(serial_number) => format!("{} ({})", self.device_type.name(), serial_number),
+            None => String::from(self.device_type.name()),
+        }
+    }
+
+    /// Add the device (type and serial number) to errors of the hid device.
+    ///
+    /// With several devices, it is otherwise not clear from a logged error which one
+    /// failed. When enabled, errors from communicating with the device (like
+    /// [Error::HidError] and [Error::WriteFailed]) are wrapped in [Error::Device], whose
+    /// message names the device and whose source is the wrapped error. Use
+    /// [Error::without_device] to match on the wrapped error. The default is false.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_error_context(true);
+    ///     if let Err(error) = device.set_brightness(50) {
+    ///         // Prints e.g. "error of device Streamdeck XL (CL12345)", then the causes
+    ///         eprintln!("{}", error);
+    ///         let mut source = std::error::Error::source(&error);
+    ///         while let Some(cause) = source {
+    ///             eprintln!("  caused by: {}", cause);
+    ///             source = cause.source();
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn set_error_context(&mut self, error_context: bool) {
+        self.error_context = error_context;
+    }
+
+    /// Wrap an error in [Error::Device], if enabled with [StreamDeckDevice::set_error_context].
+    fn in_context(&self, error: Error) -> Error {
+        if !self.error_context {
+            return error;
+        }
+        Error::Device {
+            device: self.log_context(),
+            source: Box::new(error),
+        }
+    }
+
+    /// Convert an error of the hid device, see [StreamDeckDevice::in_context].
+    fn hid_error(&self, error: hidapi::HidError) -> Error {
+        self.in_context(Error::HidError(error))
+    }
+
+    /// Wrap a hid device, that was already opened.
+    ///
+    /// This allows opening the device with custom options and using it as Streamdeck
+    /// afterwards. The device is not reset or touched otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * 'hid_device' - The opened hid device.
+    /// * 'device_type' - The type of Streamdeck the hid device is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hidapi::HidApi;
+    /// use streamdeck_hid_rs::{StreamDeckDevice, StreamDeckType};
+    /// # use streamdeck_hid_rs::hid_api_traits::HidApiTrait;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     # type HidApi = streamdeck_hid_rs::hid_api_traits::MockMockHidApi;
+    ///     let device_type = StreamDeckType::Xl;
+    ///     let hid_device = hidapi
+    ///         .open(device_type.get_vendor_id(), device_type.get_product_id())
+    ///         .unwrap();
+    ///
+    ///     let device = StreamDeckDevice::<HidApi>::from_hid_device(hid_device, device_type);
+    ///     device.set_brightness(50).unwrap();
+    /// }
+    /// ```
+    pub fn from_hid_device(
+        hid_device: API::HidDevice,
+        device_type: StreamDeckType,
+    ) -> StreamDeckDevice<API> {
+        StreamDeckDevice::new(device_type, hid_device)
+    }
+
+    /// Access the underlying hid device.
+    ///
+    /// This is an escape hatch for hidapi functions not wrapped by this library.
+    /// Be careful: Writing to the device or reading from it directly bypasses the state
+    /// kept by this object (e.g. the known button states and images), which can lead
+    /// to missed button events or wrong images when using the other methods afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::hid_api_traits::HidDeviceTrait;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     let mut report = [0x05; 32];
+    ///     device.hid_device().get_feature_report(&mut report).unwrap();
+    /// }
+    /// ```
+    pub fn hid_device(&self) -> &API::HidDevice {
+        &self.hid_device
+    }
+
     /// Lists all Streamdeck devices without opening them.
     ///
+    /// The devices are sorted by their USB ports (see [port_path]). With the libusb and
+    /// linux hidraw backends, the order stays the same across reboots, as long as the
+    /// devices are plugged into the same ports. With other backends, it is only
+    /// deterministic for one enumeration.
+    ///
     /// # Arguments
     ///
     /// * 'api' - The HidApi object to use for finding the devices.
@@ -66,307 +473,5930 @@ impl<API: HidApiTrait> StreamDeckDevice<API> {
                 result.push((device_type, device));
             }
         }
+        result.sort_by_cached_key(|(_, device)| port_path(&device.path()));
         result
     }
 
-    /// Open a Streamdeck device.
+    /// Lists all Streamdeck devices in an order, that does not depend on the enumeration.
     ///
-    /// The DeviceInfo can be taken from the return value of [list_devices].
+    /// The devices are sorted by serial number and then by their port path (see
+    /// [port_path]), so the index of a device stays the same, e.g. when showing the
+    /// devices in a UI, even if hidapi enumerates them in a different order (as it
+    /// happens with devices behind a hub). Devices without a serial number come first.
     ///
     /// # Arguments
     ///
     /// * 'api' - The HidApi object to use for finding the devices.
-    /// * 'divice_info' - The information about the device, for example taken from
-    ///                   [list_devices].
     ///
-    /// # Example
+    /// # Examples
     ///
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
-    ///     // Create a HidApi object.
-    ///     // We have to create it ourself, streamdeck-hid-rs does not hide
-    ///     // that it uses this object and we could use the same instance
-    ///     // for other hid related stuff.
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
-    ///
-    ///     // List devices lists the available devices without opening them.
-    ///     // It just lists the device types and the corresponding device id.
-    ///     let devices = StreamDeckDevice::list_devices(&hidapi);
-    ///
-    ///     println!("List of streamdeck devices:\n");
-    ///     for device in devices {
-    ///         let device = StreamDeckDevice::open(&hidapi, &device.1);
-    ///         // ... do something with device ...
+    ///     for (index, (device_type, _)) in StreamDeckDevice::list_devices_sorted(&hidapi)
+    ///         .iter()
+    ///         .enumerate()
+    ///     {
+    ///         println!("{}: {}", index, device_type.name());
     ///     }
     /// }
     /// ```
-    pub fn open(api: &API, device_info: &API::DeviceInfo) -> Result<StreamDeckDevice<API>, Error> {
-        let device_type = StreamDeckType::from_vendor_and_product_id(
-            device_info.vendor_id(),
-            device_info.product_id(),
-        );
-        if let Some(device_type) = device_type {
-            let hid_device = api
-                .open(device_type.get_vendor_id(), device_type.get_product_id())
-                .map_err(Error::HidError)?;
-            Ok(StreamDeckDevice {
-                hid_device,
-                device_type,
-            })
-        } else {
-            Err(Error::NotAStreamDeckDevice)
-        }
+    pub fn list_devices_sorted(api: &API) -> Vec<(StreamDeckType, API::DeviceInfo)> {
+        let mut result = StreamDeckDevice::list_devices(api);
+        result.sort_by_cached_key(|(_, device_info)| {
+            (
+                device_info.serial_number().unwrap_or_default(),
+                port_path(&device_info.path()),
+            )
+        });
+        result
     }
 
-    /// Open the first found StreamDeck device that is found.
+    /// Lists all Streamdeck devices, that have displays on their buttons.
     ///
-    /// If there are multiple devices, just the first one is taken. Which one this is, is random.
+    /// This returns the devices of [StreamDeckDevice::list_devices], for which
+    /// [StreamDeckType::has_displays] is true, so images can be set on all of them.
     ///
     /// # Arguments
     ///
     /// * 'api' - The HidApi object to use for finding the devices.
     ///
-    /// # Example
+    /// # Examples
     ///
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
-    ///     // Create a HidApi object.
-    ///     // We have to create it ourself, streamdeck-hid-rs does not hide
-    ///     // that it uses this object and we could use the same instance
-    ///     // for other hid related stuff.
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
     ///
-    ///     // List devices lists the available devices without opening them.
-    ///     // It just lists the device types and the corresponding device id.
-    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
-    ///     // ... do something with device ...
+    ///     for (device_type, _) in StreamDeckDevice::list_drivable_devices(&hidapi) {
+    ///         println!("{} can show images", device_type.name());
+    ///     }
     /// }
     /// ```
-    pub fn open_first_device(api: &API) -> Result<StreamDeckDevice<API>, Error> {
-        let mut all_devices = StreamDeckDevice::list_devices(api);
-        if !all_devices.is_empty() {
-            return StreamDeckDevice::open(api, &all_devices.remove(0).1);
-        }
-        Err(Error::NoDeviceFound)
+    pub fn list_drivable_devices(api: &API) -> Vec<(StreamDeckType, API::DeviceInfo)> {
+        StreamDeckDevice::list_devices(api)
+            .into_iter()
+            .filter(|(device_type, _)| device_type.has_displays())
+            .collect()
     }
 
-    /// Set the brightness of the device.
+    /// Lists all Streamdeck devices with the capabilities of their type.
+    ///
+    /// This returns the same devices as [StreamDeckDevice::list_devices], but with
+    /// [StreamDeckType::capabilities] instead of the type.
     ///
     /// # Arguments
     ///
-    /// * 'brighness' - The brighness to set, must be between 0 and 100.
+    /// * 'api' - The HidApi object to use for finding the devices.
+    ///
+    /// # Examples
     ///
-    /// # Example
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
-    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
-    ///     // make it completely dark!
-    ///     device.set_brightness(0);
+    ///
+    ///     for (capabilities, _) in StreamDeckDevice::list_devices_with_capabilities(&hidapi) {
+    ///         println!(
+    ///             "{} with {:?} buttons",
+    ///             capabilities.name, capabilities.num_buttons
+    ///         );
+    ///     }
     /// }
     /// ```
-    pub fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
-        self.hid_device
-            .send_feature_report(&self.device_type.brightness_packet(brightness))
-            .map_err(Error::HidError)?;
-        Ok(())
+    pub fn list_devices_with_capabilities(api: &API) -> Vec<(DeviceCapabilities, API::DeviceInfo)> {
+        StreamDeckDevice::list_devices(api)
+            .into_iter()
+            .map(|(device_type, info)| (device_type.capabilities(), info))
+            .collect()
     }
 
-    /// Reset communication with a device.
+    /// Lists all Streamdeck devices with their manufacturer and product strings.
     ///
-    /// This might be needed, if the connection has been interupted or the
-    /// device is in an invalid/unknown state for some other reason.
+    /// This returns the same devices as [StreamDeckDevice::list_devices], but also
+    /// reads the USB descriptor strings and the bus type, for example to show them
+    /// in a device picker.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    ///
+    /// # Examples
     ///
-    /// # Example
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
-    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
-    ///     // Ensure communication is reseted
-    ///     device.reset();
-    ///     // More things with the device
+    ///
+    ///     for device in StreamDeckDevice::describe_devices(&hidapi) {
+    ///         println!(
+    ///             "{} ({})",
+    ///             device.product.unwrap_or_default(),
+    ///             device.manufacturer.unwrap_or_default()
+    ///         );
+    ///     }
     /// }
     /// ```
-    pub fn reset(&self) -> Result<(), Error> {
-        self.hid_device
-            .write(&self.device_type.reset_key_stream_packet())
-            .map_err(Error::HidError)?;
-        self.hid_device
-            .send_feature_report(self.device_type.reset_packet())
-            .map_err(Error::HidError)?;
-        Ok(())
+    pub fn describe_devices(api: &API) -> Vec<DeviceDescription<API::DeviceInfo>> {
+        StreamDeckDevice::list_devices(api)
+            .into_iter()
+            .map(|(device_type, info)| DeviceDescription {
+                device_type,
+                manufacturer: info.manufacturer_string(),
+                product: info.product_string(),
+                bus_type: info.bus_type(),
+                info,
+            })
+            .collect()
     }
 
-    /// Set the image for a button!
+    /// Lists all Streamdeck devices, returning every physical device only once.
     ///
-    /// Changes the image on a specific button.
+    /// Some Streamdecks expose multiple HID interfaces, in which case
+    /// [StreamDeckDevice::list_devices] returns the same physical device multiple times.
+    /// This function groups the devices by their serial number and keeps the first
+    /// interface (the one with the lowest interface number).
+    /// Devices without a serial number can not be grouped and are always returned.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    ///
+    /// # Examples
     ///
-    /// # Example
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
-    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
-    ///     let mut image = image::RgbImage::new(
-    ///                   device.device_type.button_image_size().0,
-    ///                   device.device_type.button_image_size().1
-    ///     );
-    ///     // Do something with image
     ///
-    ///     device.set_button_image(0, &image);
-    ///     // More things with the device
+    ///     let devices = StreamDeckDevice::list_unique_devices(&hidapi);
+    ///
+    ///     println!("List of streamdeck devices:\n");
+    ///     for device in devices {
+    ///         println!("{}", device.0.name());
+    ///     }
     /// }
     /// ```
-    pub fn set_button_image(&self, button_id: u8, image: &RgbImage) -> Result<(), Error> {
-        let image_packages = image_packages(self.device_type.clone(), image, button_id)?;
-        for image_package in image_packages {
-            let image_package_len = image_package.len();
-            let result = self
-                .hid_device
-                .write(&image_package)
-                .map_err(Error::HidError)?;
-            if result != image_package_len {
-                return Err(Error::IncorrectWriteLengthError);
+    pub fn list_unique_devices(api: &API) -> Vec<(StreamDeckType, API::DeviceInfo)> {
+        let mut result: Vec<(StreamDeckType, API::DeviceInfo)> = Vec::new();
+
+        for (device_type, device) in StreamDeckDevice::list_devices(api) {
+            let serial = device.serial_number().filter(|serial| !serial.is_empty());
+            let duplicate = serial.as_ref().and_then(|serial| {
+                result.iter().position(|(other_type, other)| {
+                    *other_type == device_type && other.serial_number().as_ref() == Some(serial)
+                })
+            });
+            match duplicate {
+                Some(index) => {
+                    if device.interface_number() < result[index].1.interface_number() {
+                        result[index] = (device_type, device);
+                    }
+                }
+                None => result.push((device_type, device)),
             }
         }
-        Ok(())
+        result
     }
 
-    /// Wait for button events!
+    /// Open a Streamdeck device.
     ///
-    /// The Idea is, that this runs in its own thread waiting for events on the device
-    /// and calling the closure when an event occurs.
+    /// The DeviceInfo can be taken from the return value of [StreamDeckDevice::list_devices].
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'divice_info' - The information about the device, for example taken from
+    ///   [StreamDeckDevice::list_devices].
     ///
     /// # Example
+    ///
     /// ```
     /// use streamdeck_hid_rs::StreamDeckDevice;
     ///
     /// fn main() {
+    ///     // Create a HidApi object.
+    ///     // We have to create it ourself, streamdeck-hid-rs does not hide
+    ///     // that it uses this object and we could use the same instance
+    ///     // for other hid related stuff.
     ///     let hidapi = hidapi::HidApi::new().unwrap();
     ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
-    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
     ///
-    ///     // device.on_button_events(|event| {
-    ///     //    println!("Button {} changed to {:?}", event.button_id, event.state)
-    ///     // }).unwrap();
+    ///     // List devices lists the available devices without opening them.
+    ///     // It just lists the device types and the corresponding device id.
+    ///     let devices = StreamDeckDevice::list_devices(&hidapi);
+    ///
+    ///     println!("List of streamdeck devices:\n");
+    ///     for device in devices {
+    ///         let device = StreamDeckDevice::open(&hidapi, &device.1);
+    ///         // ... do something with device ...
+    ///     }
     /// }
+    /// ```
+    pub fn open(api: &API, device_info: &API::DeviceInfo) -> Result<StreamDeckDevice<API>, Error> {
+        let device_type = StreamDeckType::from_vendor_and_product_id(
+            device_info.vendor_id(),
+            device_info.product_id(),
+        );
+        if let Some(device_type) = device_type {
+            StreamDeckDevice::open_path(api, device_type, device_info)
+        } else {
+            Err(Error::NotAStreamDeckDevice)
+        }
+    }
+
+    /// Open a Streamdeck device only for reading input.
     ///
-    pub fn on_button_events<F>(&self, cb: F) -> Result<(), Error>
-    where
-        F: Fn(ButtonEvent),
-    {
-        let length: usize =
-            self.device_type.button_read_offset() + self.device_type.total_num_buttons() as usize;
-        let mut inbuffer = vec![0; length];
+    /// hidapi can not open a device read only, so the device is opened normally and
+    /// nothing is written to it when opening. Afterwards, all methods writing to the
+    /// device (setting images, brightness, reset, ...) fail with [Error::HidError],
+    /// so the device state is never changed by accident.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for opening the device.
+    /// * 'device_info' - The information about the device, for example taken from
+    ///   [StreamDeckDevice::list_devices].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let (_, info) = &StreamDeckDevice::list_devices(&hidapi)[0];
+    ///     let device = StreamDeckDevice::open_input_only(&hidapi, info).unwrap();
+    ///     // device.on_button_events(|event| {
+    ///     //    println!("Button {} changed to {:?}", event.button_id, event.state)
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn open_input_only(
+        api: &API,
+        device_info: &API::DeviceInfo,
+    ) -> Result<StreamDeckDevice<API>, Error> {
+        let mut device = StreamDeckDevice::open(api, device_info)?;
+        device.input_only = true;
+        Ok(device)
+    }
+
+    /// Open a Streamdeck device, retrying if opening fails.
+    ///
+    /// Opening can fail for a short time, for example while the official software
+    /// holds the device. Then this waits `delay` and tries again, up to `attempts`
+    /// times in total. If all attempts fail, [Error::Busy] with the last error is returned.
+    /// Every attempt opens the path of `device_info`, so another device of the same type
+    /// is never opened instead.
+    ///
+    /// Only errors that look transient (the device is busy, held exclusively or the
+    /// request timed out) are retried. Other errors, like missing permissions or a
+    /// device that is gone, are returned unchanged right away.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'device_info' - The information about the device, for example taken from
+    ///   [StreamDeckDevice::list_devices].
+    /// * 'attempts' - How often to try opening the device, at least once.
+    /// * 'delay' - How long to wait between the attempts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     for (_, device_info) in StreamDeckDevice::list_devices(&hidapi) {
+    ///         let device = StreamDeckDevice::open_with_retry(
+    ///             &hidapi,
+    ///             &device_info,
+    ///             5,
+    ///             Duration::from_millis(500),
+    ///         );
+    ///         // ... do something with device ...
+    ///     }
+    /// }
+    /// ```
+    pub fn open_with_retry(
+        api: &API,
+        device_info: &API::DeviceInfo,
+        attempts: u8,
+        delay: Duration,
+    ) -> Result<StreamDeckDevice<API>, Error> {
+        let mut attempt = 1;
+        loop {
+            match StreamDeckDevice::open(api, device_info) {
+                Err(Error::HidError(error)) if is_transient_open_error(&error) => {
+                    debug!("Opening Streamdeck failed (attempt {}): {}", attempt, error);
+                    if attempt >= attempts {
+                        return Err(Error::Busy { source: error });
+                    }
+                    attempt += 1;
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Open the first found StreamDeck device that is found.
+    ///
+    /// If there are multiple devices, just the first one is taken. Which one this is, is random.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     // Create a HidApi object.
+    ///     // We have to create it ourself, streamdeck-hid-rs does not hide
+    ///     // that it uses this object and we could use the same instance
+    ///     // for other hid related stuff.
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     // List devices lists the available devices without opening them.
+    ///     // It just lists the device types and the corresponding device id.
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // ... do something with device ...
+    /// }
+    /// ```
+    pub fn open_first_device(api: &API) -> Result<StreamDeckDevice<API>, Error> {
+        let mut all_devices = StreamDeckDevice::list_devices(api);
+        if !all_devices.is_empty() {
+            return StreamDeckDevice::open(api, &all_devices.remove(0).1);
+        }
+        Err(Error::NoDeviceFound)
+    }
+
+    /// Open the StreamDeck device at a position in a deterministic order.
+    ///
+    /// The devices are sorted like in [StreamDeckDevice::list_devices_sorted], so the
+    /// same index opens the same physical device on every run, as long as the connected
+    /// devices do not change.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'index' - The position of the device in the sorted list of devices.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     let device = StreamDeckDevice::open_device_at(&hidapi, 0).unwrap();
+    ///     // ... do something with device ...
+    /// }
+    /// ```
+    pub fn open_device_at(api: &API, index: usize) -> Result<StreamDeckDevice<API>, Error> {
+        let mut all_devices = StreamDeckDevice::list_devices_sorted(api);
+        if index >= all_devices.len() {
+            return Err(Error::NoDeviceFound);
+        }
+        let (device_type, device_info) = all_devices.swap_remove(index);
+        StreamDeckDevice::open_path(api, device_type, &device_info)
+    }
+
+    /// Open the first found StreamDeck device of a specific type.
+    ///
+    /// If there are multiple devices of this type, just the first one is taken.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'device_type' - The type of Streamdeck to open.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, StreamDeckType};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     let device = StreamDeckDevice::open_first_of_type(&hidapi, StreamDeckType::Xl).unwrap();
+    ///     // ... do something with device ...
+    /// }
+    /// ```
+    pub fn open_first_of_type(
+        api: &API,
+        device_type: StreamDeckType,
+    ) -> Result<StreamDeckDevice<API>, Error> {
+        match StreamDeckDevice::list_devices(api)
+            .into_iter()
+            .find(|(t, _)| *t == device_type)
+        {
+            Some((_, device_info)) => StreamDeckDevice::open(api, &device_info),
+            None => Err(Error::NoDeviceFound),
+        }
+    }
+
+    /// Open the first found StreamDeck device and prepare it for use.
+    ///
+    /// This does what almost every application does on startup: It opens the device
+    /// (see [StreamDeckDevice::open_first_device]), resets it, sets the brightness and
+    /// clears all buttons.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'brightness' - The brightness to set, must be between 0 and 100. With `None`
+    ///   the default of the device type is used (see [StreamDeckType::default_brightness]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     let device = StreamDeckDevice::open_ready(&hidapi, 80).unwrap();
+    ///     // ... do something with device ...
+    ///
+    ///     // Or use the default brightness of the device
+    ///     let device = StreamDeckDevice::open_ready(&hidapi, None).unwrap();
+    /// }
+    /// ```
+    pub fn open_ready(
+        api: &API,
+        brightness: impl Into<Option<u8>>,
+    ) -> Result<StreamDeckDevice<API>, Error> {
+        let device = StreamDeckDevice::open_first_device(api)?;
+        device.reset()?;
+        let brightness = brightness
+            .into()
+            .unwrap_or_else(|| device.device_type.default_brightness());
+        device.set_brightness(brightness)?;
+        device.clear_all_buttons()?;
+        Ok(device)
+    }
+
+    /// Open the first found StreamDeck device and show a splash image on all buttons.
+    ///
+    /// The device is opened (see [StreamDeckDevice::open_first_device]) and reset, then
+    /// `splash` is resized to the button image size and uploaded to every button.
+    ///
+    /// # Arguments
+    ///
+    /// * 'api' - The HidApi object to use for finding the devices.
+    /// * 'splash' - The image to show on all buttons, in any size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use image::RgbImage;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     let splash = RgbImage::from_pixel(128, 128, image::Rgb([0, 0, 255]));
+    ///     let device = StreamDeckDevice::open_with_splash(&hidapi, &splash).unwrap();
+    ///     // ... do something with device ...
+    /// }
+    /// ```
+    pub fn open_with_splash(api: &API, splash: &RgbImage) -> Result<StreamDeckDevice<API>, Error> {
+        let device = StreamDeckDevice::open_first_device(api)?;
+        device.reset()?;
+        let (width, height) = device.device_type.button_image_size();
+        let image = imageops::resize(splash, width, height, imageops::FilterType::Triangle);
+        let mut scratch = Vec::new();
+        for button_id in 0..device.device_type.total_num_buttons() {
+            device.set_button_image_into(button_id as u8, &image, &mut scratch)?;
+        }
+        Ok(device)
+    }
+
+    /// Set the brightness of the device.
+    ///
+    /// On the Streamdeck Plus, the keys and the touchscreen share the backlight,
+    /// so this sets the brightness of both.
+    ///
+    /// # Arguments
+    ///
+    /// * 'brighness' - The brighness to set, must be between 0 and 100.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // make it completely dark!
+    ///     device.set_brightness(0);
+    /// }
+    /// ```
+    pub fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+        self.send_feature_report(&self.device_type.brightness_packet(brightness))
+            .map_err(|error| self.hid_error(error))?;
+        debug!("Set brightness of {} to {}", self.log_context(), brightness);
+        *self.brightness.lock().unwrap() = Some(brightness);
+        Ok(())
+    }
+
+    /// Set how sensitive the keys react to presses.
+    ///
+    /// Only some newer models allow configuring this. Currently none of the device types
+    /// supported by this library does, so this returns [Error::Unsupported] for all of
+    /// them without sending anything.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{Error, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     match device.set_key_sensitivity(3) {
+    ///         Err(Error::Unsupported) => println!("Sensitivity can not be set"),
+    ///         result => result.unwrap(),
+    ///     }
+    /// }
+    /// ```
+    pub fn set_key_sensitivity(&self, level: u8) -> Result<(), Error> {
+        match self.device_type.key_sensitivity_packet(level) {
+            Some(packet) => self
+                .send_feature_report(&packet)
+                .map_err(|error| self.hid_error(error)),
+            None => Err(Error::Unsupported),
+        }
+    }
+
+    /// Change the brightness smoothly.
+    ///
+    /// The brightness is changed in `steps` steps, from the brightness last set with
+    /// [StreamDeckDevice::set_brightness] to `to`, spread over `duration`. If the
+    /// brightness has not been set before, it is set to `to` directly.
+    ///
+    /// # Arguments
+    ///
+    /// * 'to' - The brightness to end at, must be between 0 and 100.
+    /// * 'duration' - How long the fade takes.
+    /// * 'steps' - How many brightness values are sent, at least one.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_brightness(0).unwrap();
+    ///     device.fade_brightness(80, Duration::from_millis(200), 10).unwrap();
+    /// }
+    /// ```
+    pub fn fade_brightness(&self, to: u8, duration: Duration, steps: u8) -> Result<(), Error> {
+        let from = match *self.brightness.lock().unwrap() {
+            Some(brightness) => brightness,
+            None => return self.set_brightness(to),
+        };
+        let steps = steps.max(1) as i32;
+        let delay = duration / steps as u32;
+        for step in 1..=steps {
+            let brightness = from as i32 + (to as i32 - from as i32) * step / steps;
+            self.set_brightness(brightness as u8)?;
+            if step < steps {
+                std::thread::sleep(delay);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the images of all buttons.
+    ///
+    /// The first image is set on button 0, the second on button 1 and so on. Images
+    /// beyond the number of buttons are ignored. All images are encoded before the
+    /// first one is written, with the `rayon` feature optionally in parallel
+    /// (see `set_parallel_encoding`). Writing to the device is always sequential.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     let images = vec![image; device.device_type.total_num_buttons()];
+    ///     device.set_all_button_images(&images).unwrap();
+    /// }
+    /// ```
+    pub fn set_all_button_images(&self, images: &[RgbImage]) -> Result<(), Error> {
+        let images = &images[..images.len().min(self.device_type.total_num_buttons())];
+        let jobs: Vec<(u8, &RgbImage)> = images
+            .iter()
+            .enumerate()
+            .map(|(button_id, image)| (self.map_button_id(button_id) as u8, image))
+            .collect();
+        let (device_type, options, rotation) =
+            (&self.device_type, &self.image_options, &self.panel_rotation);
+        let encode = |(physical_id, image): &(u8, &RgbImage)| {
+            image_packages_into(
+                device_type.clone(),
+                &rotation.apply(image),
+                *physical_id,
+                options,
+                &mut Vec::new(),
+            )
+        };
+
+        #[cfg(feature = "rayon")]
+        let image_packages: Vec<Vec<Vec<u8>>> = if self.parallel_encoding {
+            use rayon::prelude::*;
+            jobs.par_iter().map(encode).collect::<Result<_, _>>()?
+        } else {
+            jobs.iter().map(encode).collect::<Result<_, _>>()?
+        };
+        #[cfg(not(feature = "rayon"))]
+        let image_packages: Vec<Vec<Vec<u8>>> =
+            jobs.iter().map(encode).collect::<Result<_, _>>()?;
+
+        for (button_id, (image, image_packages)) in images.iter().zip(image_packages).enumerate() {
+            self.write_image_packages(Some(button_id as u8), image_packages.into_iter().map(Ok))?;
+            self.store_in_framebuffer(button_id as u8, image);
+        }
+        Ok(())
+    }
+
+    /// Encode the images in [StreamDeckDevice::set_all_button_images] in parallel.
+    ///
+    /// This uses the global rayon thread pool and makes updating all buttons of large
+    /// devices (like the XL) faster. The default is false.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_parallel_encoding(true);
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn set_parallel_encoding(&mut self, parallel_encoding: bool) {
+        self.parallel_encoding = parallel_encoding;
+    }
+
+    /// Set the images of all buttons while the display is dark, then turn it on.
+    ///
+    /// Setting images while the display is on shows the old images for a short time,
+    /// which flickers when opening a device. This sets the brightness to 0, uploads
+    /// `images` to the buttons (the first image to button 0 and so on) and then sets
+    /// the brightness to `brightness`. Images beyond the number of buttons are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     let images = vec![image; device.device_type.total_num_buttons()];
+    ///     device.present(&images, 80).unwrap();
+    /// }
+    /// ```
+    pub fn present(&self, images: &[RgbImage], brightness: u8) -> Result<(), Error> {
+        self.set_brightness(0)?;
+        let mut scratch = Vec::new();
+        for (button_id, image) in images
+            .iter()
+            .take(self.device_type.total_num_buttons())
+            .enumerate()
+        {
+            self.set_button_image_into(button_id as u8, image, &mut scratch)?;
+        }
+        self.set_brightness(brightness)
+    }
+
+    /// Turn the display off, by setting the brightness to 0.
+    ///
+    /// The brightness set before is remembered and restored by
+    /// [StreamDeckDevice::power_on].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.power_off().unwrap();
+    ///     assert!(!device.is_on());
+    ///     device.power_on().unwrap();
+    /// }
+    /// ```
+    pub fn power_off(&self) -> Result<(), Error> {
+        if self.is_on() {
+            *self.brightness_before_off.lock().unwrap() = *self.brightness.lock().unwrap();
+        }
+        self.set_brightness(0)
+    }
+
+    /// Turn the display on again after [StreamDeckDevice::power_off].
+    ///
+    /// Restores the brightness set before turning the display off, or sets the
+    /// [StreamDeckType::default_brightness] if it is not known. Does nothing, if the
+    /// display is already on.
+    pub fn power_on(&self) -> Result<(), Error> {
+        if self.is_on() {
+            return Ok(());
+        }
+        let brightness = self
+            .brightness_before_off
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| self.device_type.default_brightness());
+        self.set_brightness(brightness)
+    }
+
+    /// Check if the display is on, i.e. the brightness last set is not 0.
+    ///
+    /// If the brightness has not been set, the display is assumed to be on.
+    pub fn is_on(&self) -> bool {
+        *self.brightness.lock().unwrap() != Some(0)
+    }
+
+    /// Send a harmless request to the device, to keep the connection active.
+    ///
+    /// This is a workaround for systems, where the device stops sending input after
+    /// being idle for a long time. Call it regularly, on a schedule of your choice.
+    /// It sets the brightness again to the last value set with
+    /// [StreamDeckDevice::set_brightness], or reads the firmware version if the
+    /// brightness has not been set.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_brightness(50).unwrap();
+    ///     // ... from time to time ...
+    ///     device.keepalive().unwrap();
+    /// }
+    /// ```
+    pub fn keepalive(&self) -> Result<(), Error> {
+        let brightness = *self.brightness.lock().unwrap();
+        match brightness {
+            Some(brightness) => self.set_brightness(brightness),
+            None => {
+                let mut report = self.device_type.firmware_version_report();
+                self.hid_device
+                    .get_feature_report(&mut report)
+                    .map_err(|error| self.hid_error(error))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Keep the connection active and check for input, e.g. from a watchdog thread.
+    ///
+    /// This sends the [StreamDeckDevice::keepalive] request, then waits up to 100ms
+    /// for a report with [StreamDeckDevice::input_available]. Like there, a report read
+    /// is applied to the button states and its events are not delivered to an event
+    /// loop, so do not call this while an event loop runs in another thread.
+    ///
+    /// Returns true if the device sent a report. An idle device does not send reports,
+    /// so false only means there was no input. A device that is gone returns an error.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     match device.heartbeat() {
+    ///         Ok(true) => println!("Input received"),
+    ///         Ok(false) => println!("Idle"),
+    ///         Err(error) => println!("Device lost: {}", error),
+    ///     }
+    /// }
+    /// ```
+    pub fn heartbeat(&self) -> Result<bool, Error> {
+        self.keepalive()?;
+        self.input_available(HEARTBEAT_READ_TIMEOUT_MS)
+    }
+
+    /// Reset communication with a device.
+    ///
+    /// This might be needed, if the connection has been interupted or the
+    /// device is in an invalid/unknown state for some other reason.
+    /// On the Streamdeck Plus, the touchscreen is cleared to black as well.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // Ensure communication is reseted
+    ///     device.reset();
+    ///     // More things with the device
+    /// }
+    /// ```
+    pub fn reset(&self) -> Result<(), Error> {
+        self.write(&self.device_type.reset_key_stream_packet())
+            .map_err(|error| self.hid_error(error))?;
+        self.send_feature_report(self.device_type.reset_packet())
+            .map_err(|error| self.hid_error(error))?;
+        if let Some((width, height)) = self.device_type.touchscreen_size() {
+            let image_packages = touchscreen_image_packages(
+                self.device_type.clone(),
+                &RgbImage::new(width, height),
+            )?;
+            self.write_image_packages(None, image_packages.into_iter().map(Ok))?;
+        }
+        info!("Reset {}", self.log_context());
+        self.clear_framebuffer();
+        Ok(())
+    }
+
+    /// Clear the images of all buttons, without resetting the device.
+    ///
+    /// Unlike [StreamDeckDevice::reset], this only writes the key stream reset
+    /// (see [StreamDeckType::reset_key_stream_command]), which blanks the images.
+    /// The reset feature report is not sent, so input reporting is not interrupted
+    /// and an event loop in another thread keeps receiving events.
+    /// The touchscreen of the Streamdeck Plus is not cleared.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.clear_images_only().unwrap();
+    /// }
+    /// ```
+    pub fn clear_images_only(&self) -> Result<(), Error> {
+        self.write(&self.device_type.reset_key_stream_packet())
+            .map_err(|error| self.hid_error(error))?;
+        self.clear_framebuffer();
+        Ok(())
+    }
+
+    /// Run a diagnostic of the device and report which steps succeeded.
+    ///
+    /// The device is reset, set to its [StreamDeckType::default_brightness], a test
+    /// pattern is uploaded to every button and a few reports are read (waiting at most
+    /// 100ms each). A failing step does not stop the test, all steps
+    /// are always attempted. The test pattern stays on the buttons afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let report = device.self_test();
+    ///     for step in report.failures() {
+    ///         println!("{} failed: {:?}", step.name, step.result);
+    ///     }
+    /// }
+    /// ```
+    pub fn self_test(&self) -> SelfTestReport {
+        let mut steps = Vec::new();
+        let mut step =
+            |name: String, result: Result<(), Error>| steps.push(SelfTestStep { name, result });
+        step(String::from("reset"), self.reset());
+        step(
+            String::from("set brightness"),
+            self.set_brightness(self.device_type.default_brightness()),
+        );
+        let test_pattern = self.device_type.test_pattern(TestPattern::Checker);
+        for button_id in 0..self.device_type.total_num_buttons() as u8 {
+            step(
+                format!("set image of button {}", button_id),
+                self.set_button_image(button_id, &test_pattern),
+            );
+        }
+        for read in 0..SELF_TEST_READS {
+            step(
+                format!("read report {}", read),
+                self.read_button_events_timeout(Some(SELF_TEST_READ_TIMEOUT))
+                    .map(|_| ()),
+            );
+        }
+        SelfTestReport { steps }
+    }
+
+    /// Check if the device is still connected and responding.
+    ///
+    /// This reads the firmware version feature report from the device, so it
+    /// costs a round trip over USB. Returns false if reading the report fails,
+    /// for example because the device has been disconnected.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     if !device.is_alive() {
+    ///         println!("The device has been disconnected");
+    ///     }
+    /// }
+    /// ```
+    pub fn is_alive(&self) -> bool {
+        let mut report = self.device_type.firmware_version_report();
+        self.hid_device.get_feature_report(&mut report).is_ok()
+    }
+
+    /// Set the image for a button!
+    ///
+    /// Changes the image on a specific button.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let mut image = image::RgbImage::new(
+    ///                   device.device_type.button_image_size().0,
+    ///                   device.device_type.button_image_size().1
+    ///     );
+    ///     // Do something with image
+    ///
+    ///     device.set_button_image(0, &image);
+    ///     // More things with the device
+    /// }
+    /// ```
+    pub fn set_button_image(&self, button_id: u8, image: &RgbImage) -> Result<(), Error> {
+        self.write_image_packages(Some(button_id), self.image_packages_iter(button_id, image))?;
+        self.store_in_framebuffer(button_id, image);
+        Ok(())
+    }
+
+    /// Set the image for a button, displayed darker than the other buttons.
+    ///
+    /// The brightness of the device applies to all buttons, this emulates a lower
+    /// brightness for one button by multiplying the pixel values with `factor`
+    /// (clamped to 0.0 - 1.0) before uploading.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     // Show an inactive button at half brightness
+    ///     device.set_button_image_dimmed(0, &image, 0.5).unwrap();
+    /// }
+    /// ```
+    pub fn set_button_image_dimmed(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+        factor: f32,
+    ) -> Result<(), Error> {
+        self.set_button_image(button_id, &dim_image(image, factor))
+    }
+
+    /// Set the image for a button given by its name.
+    ///
+    /// Only devices with few buttons have named buttons, see [StreamDeckType::named_buttons].
+    /// Returns [Error::UnknownButtonName] if the device has no button with the name.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, StreamDeckType, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     if device.device_type == StreamDeckType::Mini {
+    ///         let image = device.device_type.test_pattern(TestPattern::Checker);
+    ///         device.set_button_image_named("TopLeft", &image).unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn set_button_image_named(&self, name: &str, image: &RgbImage) -> Result<(), Error> {
+        let button_id = self
+            .device_type
+            .named_buttons()
+            .into_iter()
+            .find(|(button_name, _)| *button_name == name)
+            .map(|(_, button_id)| button_id)
+            .ok_or_else(|| Error::UnknownButtonName(String::from(name)))?;
+        self.set_button_image(button_id, image)
+    }
+
+    /// Set the images of the buttons present in a map, the other buttons are not changed.
+    ///
+    /// The images are uploaded in the order of the button ids. Uploading stops at
+    /// the first error, the buttons before it have their new image already.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let mut changed = HashMap::new();
+    ///     changed.insert(1, device.device_type.test_pattern(TestPattern::Checker));
+    ///     changed.insert(4, device.device_type.test_pattern(TestPattern::Gradient));
+    ///     device.set_button_images_map(&changed).unwrap();
+    /// }
+    /// ```
+    pub fn set_button_images_map(&self, images: &HashMap<u8, RgbImage>) -> Result<(), Error> {
+        let mut button_ids: Vec<u8> = images.keys().copied().collect();
+        button_ids.sort_unstable();
+        for button_id in button_ids {
+            self.set_button_image(button_id, &images[&button_id])?;
+        }
+        Ok(())
+    }
+
+    /// Fill a button with a color showing a value, like a status light.
+    ///
+    /// The color goes from green (0.0) over yellow (0.5) to red (1.0), which is useful
+    /// for displaying e.g. the CPU load or a temperature. See [StreamDeckType::gauge_image].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let cpu_load = 0.7;
+    ///     device.set_button_gauge(0, cpu_load).unwrap();
+    /// }
+    /// ```
+    pub fn set_button_gauge(&self, button_id: u8, fraction: f32) -> Result<(), Error> {
+        self.set_button_image(button_id, &self.device_type.gauge_image(fraction))
+    }
+
+    /// Set the color buttons are cleared to.
+    ///
+    /// Used by [StreamDeckDevice::clear_button_image] and
+    /// [StreamDeckDevice::clear_all_buttons]. The default is black.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_default_background(image::Rgb([20, 20, 40]));
+    ///     device.clear_all_buttons().unwrap();
+    /// }
+    /// ```
+    pub fn set_default_background(&mut self, color: Rgb<u8>) {
+        self.default_background = color;
+    }
+
+    /// Set a button to the default background, see [StreamDeckDevice::set_default_background].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.clear_button_image(0).unwrap();
+    /// }
+    /// ```
+    pub fn clear_button_image(&self, button_id: u8) -> Result<(), Error> {
+        self.set_button_image(button_id, &self.background_image())
+    }
+
+    /// Flash pressed buttons brighter, as feedback for the press.
+    ///
+    /// The brightness of the device applies to all buttons, so this is emulated per
+    /// button: when the event loops (or other methods reading input) see a button going
+    /// down, a brightened version of its image (the pixel values multiplied with `factor`,
+    /// at least 1.0) is uploaded. When the button goes up, the original image is restored.
+    /// Only buttons with an image set through this object flash, and black pixels stay
+    /// black. Each press costs two image uploads, done before the events are delivered.
+    /// A failed upload is logged and does not fail reading the input. Devices opened with
+    /// [StreamDeckDevice::open_input_only] and presses discarded by
+    /// [StreamDeckDevice::suppress_input] do not flash.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_press_flash(true, 1.5);
+    ///     let image = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     device.set_button_image(0, &image).unwrap();
+    ///     // device.on_button_events(|event| { ... }).unwrap();
+    /// }
+    /// ```
+    pub fn set_press_flash(&mut self, enabled: bool, factor: f32) {
+        self.press_flash = enabled.then_some(factor);
+    }
+
+    /// Upload the brightened or original images for button events, see
+    /// [StreamDeckDevice::set_press_flash].
+    ///
+    /// A failed upload is only logged, the events are delivered regardless.
+    fn flash_pressed_buttons(&self, events: &[ButtonEvent]) {
+        let factor = match self.press_flash {
+            Some(factor) if !self.input_only => factor,
+            _ => return,
+        };
+        for event in events {
+            let button_id = event.button_id as u8;
+            let image = match self
+                .framebuffer
+                .lock()
+                .unwrap()
+                .get(event.button_id as usize)
+            {
+                Some(Some(image)) => image.clone(),
+                _ => continue,
+            };
+            // The framebuffer keeps the original image, to restore it
+            let image = match event.state {
+                ButtonState::Down => brighten_image(&image, factor),
+                ButtonState::Up => image,
+            };
+            if let Err(error) = self
+                .write_image_packages(Some(button_id), self.image_packages_iter(button_id, &image))
+            {
+                warn!("Flashing button {} failed: {}", button_id, error);
+            }
+        }
+    }
+
+    /// Reset the image of a single button to black.
+    ///
+    /// The Streamdecks can only reset the images of all buttons at once (see
+    /// [StreamDeckDevice::clear_images_only]), there is no command for a single button.
+    /// So this uploads a black image to the button, regardless of the default background.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.reset_button(3).unwrap();
+    /// }
+    /// ```
+    pub fn reset_button(&self, button_id: u8) -> Result<(), Error> {
+        let (width, height) = self.device_type.button_image_size();
+        self.set_button_image(button_id, &RgbImage::new(width, height))
+    }
+
+    /// Set all buttons to the default background, see [StreamDeckDevice::set_default_background].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.clear_all_buttons().unwrap();
+    /// }
+    /// ```
+    pub fn clear_all_buttons(&self) -> Result<(), Error> {
+        let image = self.background_image();
+        let mut scratch = Vec::new();
+        for button_id in 0..self.device_type.total_num_buttons() {
+            self.set_button_image_into(button_id as u8, &image, &mut scratch)?;
+        }
+        Ok(())
+    }
+
+    /// A button image in the default background color.
+    fn background_image(&self) -> RgbImage {
+        let (width, height) = self.device_type.button_image_size();
+        RgbImage::from_pixel(width, height, self.default_background)
+    }
+
+    /// Change the images of all buttons with an animated transition.
+    ///
+    /// Image `i` is uploaded to button `i`, buttons without an image are not changed.
+    /// The transition takes about `duration`. With [TransitionStyle::Fade] the
+    /// brightness must have been set with [StreamDeckDevice::set_brightness] before,
+    /// otherwise the images are changed without fading.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern, TransitionStyle};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let images = vec![
+    ///         device.device_type.test_pattern(TestPattern::Gradient);
+    ///         device.device_type.total_num_buttons()
+    ///     ];
+    ///     device
+    ///         .transition_to(&images, TransitionStyle::LeftToRight, Duration::from_millis(300))
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn transition_to(
+        &self,
+        images: &[RgbImage],
+        style: TransitionStyle,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let num_keys = images.len().min(self.device_type.total_num_buttons());
+        let mut button_ids: Vec<usize> = (0..num_keys).collect();
+        let mut scratch = Vec::new();
+        match style {
+            TransitionStyle::Fade => {
+                let brightness = *self.brightness.lock().unwrap();
+                if let Some(brightness) = brightness {
+                    self.fade_brightness(0, duration / 2, TRANSITION_FADE_STEPS)?;
+                    for button_id in button_ids {
+                        self.set_button_image_into(
+                            button_id as u8,
+                            &images[button_id],
+                            &mut scratch,
+                        )?;
+                    }
+                    return self.fade_brightness(brightness, duration / 2, TRANSITION_FADE_STEPS);
+                }
+            }
+            TransitionStyle::LeftToRight => {
+                button_ids.sort_by_key(|button_id| {
+                    let (row, column) = self
+                        .device_type
+                        .button_coordinates(*button_id as u32)
+                        .unwrap_or_default();
+                    (column, row)
+                });
+            }
+            TransitionStyle::Random => shuffle(&mut button_ids),
+        }
+        let delay = duration / num_keys.max(1) as u32;
+        for (index, button_id) in button_ids.into_iter().enumerate() {
+            if index > 0 {
+                std::thread::sleep(delay);
+            }
+            self.set_button_image_into(button_id as u8, &images[button_id], &mut scratch)?;
+        }
+        Ok(())
+    }
+
+    /// Render the images last uploaded to the buttons into one image, laid out like the panel.
+    ///
+    /// This is a debugging aid to see what has been drawn, without looking at the device.
+    /// The buttons are separated by a gap of [CONTACT_SHEET_GAP] pixels in dark gray,
+    /// buttons without a known image are black.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Checker);
+    ///     device.set_button_image(0, &image).unwrap();
+    ///     let sheet = device.render_panel_contact_sheet();
+    ///     // sheet.save("panel.png").unwrap();
+    /// }
+    /// ```
+    pub fn render_panel_contact_sheet(&self) -> RgbImage {
+        let (rows, columns) = self.device_type.num_buttons();
+        let (width, height) = self.device_type.button_image_size();
+        let mut sheet = RgbImage::from_pixel(
+            columns * (width + CONTACT_SHEET_GAP) + CONTACT_SHEET_GAP,
+            rows * (height + CONTACT_SHEET_GAP) + CONTACT_SHEET_GAP,
+            CONTACT_SHEET_GAP_COLOR,
+        );
+        let framebuffer = self.framebuffer.lock().unwrap();
+        for (button_id, image) in framebuffer.iter().enumerate() {
+            let Some((row, column)) = self.device_type.button_coordinates(button_id as u32) else {
+                continue;
+            };
+            let x = (CONTACT_SHEET_GAP + column * (width + CONTACT_SHEET_GAP)) as i64;
+            let y = (CONTACT_SHEET_GAP + row * (height + CONTACT_SHEET_GAP)) as i64;
+            imageops::replace(&mut sheet, &RgbImage::new(width, height), x, y);
+            if let Some(image) = image {
+                imageops::replace(&mut sheet, image, x, y);
+            }
+        }
+        sheet
+    }
+
+    /// Upload the images of this device to another device.
+    ///
+    /// The images last uploaded to this device are sent to the same buttons on `other`.
+    /// If the button image sizes differ, the images are resized. Buttons that do not
+    /// exist on `other` and buttons without a known image are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let primary = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let backup = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // ... set images on primary ...
+    ///     primary.mirror_to(&backup).unwrap();
+    /// }
+    /// ```
+    pub fn mirror_to(&self, other: &StreamDeckDevice<API>) -> Result<(), Error> {
+        let images = self.framebuffer.lock().unwrap().clone();
+        let (width, height) = other.device_type.button_image_size();
+        for (button_id, image) in images
+            .iter()
+            .enumerate()
+            .take(other.device_type.total_num_buttons())
+        {
+            if let Some(image) = image {
+                if image.dimensions() == (width, height) {
+                    other.set_button_image(button_id as u8, image)?;
+                } else {
+                    let resized =
+                        imageops::resize(image, width, height, imageops::FilterType::Triangle);
+                    other.set_button_image(button_id as u8, &resized)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the image on the info screen of the Streamdeck Neo.
+    ///
+    /// The image must have the size returned by [StreamDeckType::neo_info_image_size].
+    /// Returns [Error::Unsupported] for devices without an info screen.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, StreamDeckType};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     if let Some((width, height)) = device.device_type.neo_info_image_size() {
+    ///         let image = image::RgbImage::new(width, height);
+    ///         device.set_neo_info_image(&image).unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn set_neo_info_image(&self, image: &RgbImage) -> Result<(), Error> {
+        let image_packages = info_image_packages(self.device_type.clone(), image)?;
+        self.write_image_packages(None, image_packages.into_iter().map(Ok))
+    }
+
+    /// Set the image for a button from an already encoded image.
+    ///
+    /// The image must be encoded and transformed as described by
+    /// [StreamDeckType::image_spec], it is sent to the device as it is. If the image
+    /// is not in the image format of the device, [Error::InvalidEncodedImage] is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let encoded = std::fs::read("button.jpg");
+    ///     # let encoded: std::io::Result<Vec<u8>> = Ok(vec![0xFF, 0xD8, 0xFF, 0xD9]);
+    ///     device.set_button_image_raw(0, &encoded.unwrap()).unwrap();
+    /// }
+    /// ```
+    pub fn set_button_image_raw(&self, button_id: u8, encoded_image: &[u8]) -> Result<(), Error> {
+        if !self.device_type.is_valid_encoded_image(encoded_image) {
+            return Err(Error::InvalidEncodedImage);
+        }
+        self.write_image_packages(
+            Some(button_id),
+            button_packages(
+                self.device_type.clone(),
+                encoded_image,
+                self.map_button_id(button_id as usize) as u8,
+            )?,
+        )?;
+        // The image is not known as RgbImage
+        self.forget_in_framebuffer(button_id as usize);
+        Ok(())
+    }
+
+    /// Set the image for a button, lowering the jpeg quality until it fits `max_bytes`.
+    ///
+    /// The highest quality, for which the encoded image has at most `max_bytes`, is
+    /// searched for (binary search, so the image is encoded about 7 times) and returned.
+    /// This makes it possible to guarantee an upper bound for the upload time.
+    /// Returns [Error::ImageTooLarge] if the image does not fit even with quality 1,
+    /// and [Error::Unsupported] for devices not using jpeg images.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     let quality = device.set_button_image_within_budget(0, &image, 4096).unwrap();
+    ///     println!("Uploaded with quality {}", quality);
+    /// }
+    /// ```
+    pub fn set_button_image_within_budget(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+        max_bytes: usize,
+    ) -> Result<u8, Error> {
+        if self.device_type.button_image_format() != StreamDeckImageFormat::Jpeg {
+            return Err(Error::Unsupported);
+        }
+        let rotated = self.rotate_for_panel(image);
+        let encode = |quality: u8| -> Result<Vec<u8>, Error> {
+            let options = ImageOptions {
+                jpeg_quality: Some(quality),
+                ..self.image_options.clone()
+            };
+            let mut encoded_image = Vec::new();
+            encode_button_image(&self.device_type, &rotated, &options, &mut encoded_image)?;
+            Ok(encoded_image)
+        };
+
+        let mut best = encode(1)?;
+        if best.len() > max_bytes {
+            return Err(Error::ImageTooLarge {
+                size: best.len(),
+                max_bytes,
+            });
+        }
+        let (mut low, mut high) = (1u8, 100u8);
+        while low < high {
+            let quality = (low + high).div_ceil(2);
+            let encoded_image = encode(quality)?;
+            if encoded_image.len() <= max_bytes {
+                low = quality;
+                best = encoded_image;
+            } else {
+                high = quality - 1;
+            }
+        }
+
+        self.write_image_packages(
+            Some(button_id),
+            button_packages(
+                self.device_type.clone(),
+                best,
+                self.map_button_id(button_id as usize) as u8,
+            )?,
+        )?;
+        self.store_in_framebuffer(button_id, image);
+        Ok(low)
+    }
+
+    /// Set the image for a button without any transformation (diagnostic tool).
+    ///
+    /// The image is sent to the device exactly as given, the rotation needed by the
+    /// device type and the panel rotation are not applied and `button_id` is the
+    /// physical button id. This is meant to diagnose orientation problems, use
+    /// [StreamDeckDevice::set_button_image] for normal use.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = image::RgbImage::new(
+    ///                   device.device_type.button_image_size().0,
+    ///                   device.device_type.button_image_size().1
+    ///     );
+    ///     // Check how the raw image is displayed
+    ///     device.set_button_image_no_transform(0, &image).unwrap();
+    /// }
+    /// ```
+    pub fn set_button_image_no_transform(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+    ) -> Result<(), Error> {
+        let options = ImageOptions {
+            native_orientation: true,
+            ..self.image_options.clone()
+        };
+        let image_packages =
+            image_packages_iter(self.device_type.clone(), image, button_id, &options);
+        self.write_image_packages(Some(button_id), image_packages)?;
+        // The displayed image does not match any image in user orientation
+        self.forget_in_framebuffer(self.map_button_id(button_id as usize));
+        Ok(())
+    }
+
+    /// Set the image for a button, but only if it differs from the current image.
+    ///
+    /// The images uploaded to the buttons are remembered. If `image` is the same as the
+    /// image last uploaded to the button, nothing is sent to the device.
+    /// Returns true if the image has been uploaded.
+    ///
+    /// The remembered images are forgotten on [StreamDeckDevice::reset].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = image::RgbImage::new(
+    ///                   device.device_type.button_image_size().0,
+    ///                   device.device_type.button_image_size().1
+    ///     );
+    ///     // In a redraw loop, only changed images are uploaded
+    ///     assert!(device.set_button_image_if_changed(0, &image).unwrap());
+    ///     assert!(!device.set_button_image_if_changed(0, &image).unwrap());
+    /// }
+    /// ```
+    pub fn set_button_image_if_changed(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+    ) -> Result<bool, Error> {
+        if !self.button_needs_update(button_id, image) {
+            return Ok(false);
+        }
+        self.set_button_image(button_id, image)?;
+        Ok(true)
+    }
+
+    /// Check if `image` differs from the image last uploaded to the button.
+    ///
+    /// Nothing is sent to the device. Returns true if the image of the button is not
+    /// known, for example after [StreamDeckDevice::reset]. This allows skipping the
+    /// rendering of an image, that is already displayed.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = device.device_type.test_pattern(TestPattern::Checker);
+    ///     device.set_button_image(0, &image).unwrap();
+    ///     assert!(!device.button_needs_update(0, &image));
+    /// }
+    /// ```
+    pub fn button_needs_update(&self, button_id: u8, image: &RgbImage) -> bool {
+        match self.cache_hash_mode {
+            CacheHashMode::Full => !self
+                .framebuffer
+                .lock()
+                .unwrap()
+                .get(button_id as usize)
+                .and_then(Option::as_ref)
+                .is_some_and(|current| current == image),
+            // Only the new image is hashed, the hash of the current one is stored
+            CacheHashMode::Fast => {
+                let hash = fast_image_hash(image);
+                self.framebuffer_hashes
+                    .lock()
+                    .unwrap()
+                    .get(button_id as usize)
+                    .copied()
+                    .flatten()
+                    != Some(hash)
+            }
+        }
+    }
+
+    /// Set how new images are compared with the images last uploaded to the buttons.
+    ///
+    /// This is used by [StreamDeckDevice::button_needs_update] and
+    /// [StreamDeckDevice::set_button_image_if_changed]. [CacheHashMode::Fast] only
+    /// compares every 8th row, which is much cheaper for large images but misses changes
+    /// in the other rows. Use it only, if images change in larger areas (e.g. text or
+    /// icons, not single pixels). The default is [CacheHashMode::Full].
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{CacheHashMode, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_cache_hash_mode(CacheHashMode::Fast);
+    /// }
+    /// ```
+    pub fn set_cache_hash_mode(&mut self, mode: CacheHashMode) {
+        self.cache_hash_mode = mode;
+        let framebuffer = self.framebuffer.lock().unwrap();
+        let mut hashes = self.framebuffer_hashes.lock().unwrap();
+        for (hash, image) in hashes.iter_mut().zip(framebuffer.iter()) {
+            *hash = match mode {
+                CacheHashMode::Full => None,
+                CacheHashMode::Fast => image.as_ref().map(fast_image_hash),
+            };
+        }
+    }
+
+    /// Set the image for a button, using a caller provided scratch buffer.
+    ///
+    /// Works like [StreamDeckDevice::set_button_image], but the encoded image is written
+    /// into `scratch` instead of a newly allocated buffer. `scratch` is cleared and
+    /// resized as needed, so the same buffer can be reused for every upload.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = image::RgbImage::new(
+    ///                   device.device_type.button_image_size().0,
+    ///                   device.device_type.button_image_size().1
+    ///     );
+    ///     let mut scratch = Vec::new();
+    ///     for button_id in 0..device.device_type.total_num_buttons() {
+    ///         device.set_button_image_into(button_id as u8, &image, &mut scratch);
+    ///     }
+    /// }
+    /// ```
+    pub fn set_button_image_into(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+        scratch: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let image_packages = self.image_packages(button_id, image, scratch)?;
+        self.write_image_packages(Some(button_id), image_packages.into_iter().map(Ok))?;
+        self.store_in_framebuffer(button_id, image);
+        Ok(())
+    }
+
+    /// Set the image for a button and report how long the upload took.
+    ///
+    /// Works like [StreamDeckDevice::set_button_image], but measures the time needed
+    /// for encoding and for writing. This helps to find out if encoding or the USB
+    /// connection is the bottleneck.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let image = image::RgbImage::new(
+    ///                   device.device_type.button_image_size().0,
+    ///                   device.device_type.button_image_size().1
+    ///     );
+    ///     let stats = device.set_button_image_stats(0, &image).unwrap();
+    ///     println!("Encoding took {:?}, writing took {:?}", stats.encode, stats.write);
+    /// }
+    /// ```
+    pub fn set_button_image_stats(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+    ) -> Result<UploadStats, Error> {
+        let start = Instant::now();
+        let image_packages = self.image_packages(button_id, image, &mut Vec::new())?;
+        let encode = start.elapsed();
+        let packets = image_packages.len();
+
+        let start = Instant::now();
+        self.write_image_packages(Some(button_id), image_packages.into_iter().map(Ok))?;
+        let write = start.elapsed();
+        self.store_in_framebuffer(button_id, image);
+        Ok(UploadStats {
+            encode,
+            write,
+            packets,
+        })
+    }
+
+    /// Measure the upload throughput, by uploading a test pattern to button 0 repeatedly.
+    ///
+    /// The [TestPattern::Gradient] is uploaded `iterations` times. The time includes
+    /// encoding the image every time, as it is for [StreamDeckDevice::set_button_image].
+    /// This helps comparing USB setups (ports, hubs, cables). Button 0 shows the test
+    /// pattern afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let benchmark = device.benchmark_upload(20).unwrap();
+    ///     println!(
+    ///         "{:.0} bytes/s, {:.0} packets/s",
+    ///         benchmark.bytes_per_second, benchmark.packets_per_second
+    ///     );
+    /// }
+    /// ```
+    pub fn benchmark_upload(&self, iterations: usize) -> Result<UploadBenchmark, Error> {
+        let image = self.device_type.test_pattern(TestPattern::Gradient);
+        let mut packets = 0;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            packets += self.set_button_image_stats(0, &image)?.packets;
+        }
+        let elapsed = start.elapsed();
+        let bytes = packets * self.device_type.image_package_size();
+        let per_second = |count: usize| {
+            if elapsed.is_zero() {
+                0.0
+            } else {
+                count as f64 / elapsed.as_secs_f64()
+            }
+        };
+        Ok(UploadBenchmark {
+            iterations,
+            packets,
+            bytes,
+            elapsed,
+            bytes_per_second: per_second(bytes),
+            packets_per_second: per_second(packets),
+        })
+    }
+
+    /// Estimate how many bytes are written to set an image on every button.
+    ///
+    /// `sample` is encoded once, as if uploaded to a button, and the size of all its
+    /// packages (including headers and padding) is multiplied by the number of buttons.
+    /// Nothing is written to the device. As the encoded size depends on the image
+    /// content for jpeg, use a sample similar to the real images.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let sample = device.device_type.test_pattern(TestPattern::Gradient);
+    ///     let bytes = device.estimate_full_refresh_bytes(&sample).unwrap();
+    ///     println!("A full refresh writes {} bytes", bytes);
+    /// }
+    /// ```
+    pub fn estimate_full_refresh_bytes(&self, sample: &RgbImage) -> Result<usize, Error> {
+        let packets = self.image_packages(0, sample, &mut Vec::new())?.len();
+        Ok(packets * self.device_type.image_package_size() * self.device_type.total_num_buttons())
+    }
+
+    /// Encode the frames of an animation ahead of time.
+    ///
+    /// Encoding images is the expensive part of uploading them. With this, it is done
+    /// once and [StreamDeckDevice::play_prepared_animation] only writes the packets.
+    /// The frames are prepared with the current settings of the device (e.g. the panel
+    /// rotation), changing them afterwards does not affect the prepared animation.
+    ///
+    /// # Arguments
+    ///
+    /// * 'frames' - The images of the animation, all in the button image size.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::{StreamDeckDevice, TestPattern};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let frames = [
+    ///         device.device_type.test_pattern(TestPattern::Checker),
+    ///         device.device_type.test_pattern(TestPattern::Gradient),
+    ///     ];
+    ///     let animation = device.prepare_animation(&frames).unwrap();
+    ///     for _ in 0..3 {
+    ///         device
+    ///             .play_prepared_animation(0, &animation, Duration::from_millis(100))
+    ///             .unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn prepare_animation(&self, frames: &[RgbImage]) -> Result<PreparedAnimation, Error> {
+        let frames = frames
+            .iter()
+            .map(|frame| {
+                let mut encoded_image = Vec::new();
+                encode_button_image(
+                    &self.device_type,
+                    &self.rotate_for_panel(frame),
+                    &self.image_options,
+                    &mut encoded_image,
+                )?;
+                Ok(encoded_image)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(PreparedAnimation {
+            device_type: self.device_type.clone(),
+            frames,
+        })
+    }
+
+    /// Show the frames of a prepared animation on a button.
+    ///
+    /// The frames are written one after another, waiting `delay` between them.
+    /// The last frame stays on the button. Returns [Error::Unsupported] if the
+    /// animation was prepared for another type of device.
+    ///
+    /// See [StreamDeckDevice::prepare_animation] for an example.
+    pub fn play_prepared_animation(
+        &self,
+        button_id: u8,
+        animation: &PreparedAnimation,
+        delay: Duration,
+    ) -> Result<(), Error> {
+        if animation.device_type != self.device_type {
+            return Err(Error::Unsupported);
+        }
+        let physical_id = self.map_button_id(button_id as usize) as u8;
+        for (index, frame) in animation.frames.iter().enumerate() {
+            if index > 0 {
+                std::thread::sleep(delay);
+            }
+            self.write_image_packages(
+                Some(button_id),
+                button_packages(self.device_type.clone(), frame, physical_id)?,
+            )?;
+        }
+        // The source images of the frames are not kept
+        self.forget_in_framebuffer(button_id as usize);
+        Ok(())
+    }
+
+    /// Assume that button images are already in the orientation of the device.
+    ///
+    /// Normally images are rotated before sending them, so that they are displayed
+    /// correctly. If your images are already rotated for the device (see
+    /// [crate::ImageSpec::transformation]), set this to true to skip the rotation.
+    /// The default is false.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_assume_native_orientation(true);
+    /// }
+    /// ```
+    pub fn set_assume_native_orientation(&mut self, native_orientation: bool) {
+        self.image_options.native_orientation = native_orientation;
+        self.clear_framebuffer();
+    }
+
+    /// Log the data sent to the device instead of sending it.
+    ///
+    /// When enabled, all writes and feature reports that would change the device are
+    /// logged with [log::debug] as hex dump, which helps debugging the protocol.
+    /// Reading from the device is not affected. The default is false.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_dry_run(true);
+    ///     // Only logged
+    ///     device.set_brightness(50).unwrap();
+    /// }
+    /// ```
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Limit the concurrent image uploads with a governor shared between devices.
+    ///
+    /// Every image upload waits for a permit of the governor before writing, and
+    /// holds it until all packets of the image are written.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::Arc;
+    /// use streamdeck_hid_rs::{StreamDeckDevice, UploadGovernor};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_upload_governor(Arc::new(UploadGovernor::new(1)));
+    /// }
+    /// ```
+    pub fn set_upload_governor(&mut self, upload_governor: Arc<UploadGovernor>) {
+        self.upload_governor = Some(upload_governor);
+    }
+
+    /// Pause between writing the packets of an image.
+    ///
+    /// Some USB setups corrupt images, when the packets are written too fast.
+    /// A small delay (a few milliseconds) can help there, but makes uploads slower.
+    /// The default is no delay.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_interpacket_delay(Duration::from_millis(2));
+    /// }
+    /// ```
+    pub fn set_interpacket_delay(&mut self, delay: Duration) {
+        self.interpacket_delay = delay;
+    }
+
+    /// Set what happens, when a button image does not have the exact expected size.
+    ///
+    /// With [DimensionPolicy::Strict] (the default) such images are rejected with
+    /// [Error::DimensionMismatch]. With [DimensionPolicy::AutoFit] they are padded with
+    /// black or cropped on the right and bottom, which helps when resizing with
+    /// rounding errors produced an image that is one pixel off.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{DimensionPolicy, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_dimension_policy(DimensionPolicy::AutoFit);
+    /// }
+    /// ```
+    pub fn set_dimension_policy(&mut self, policy: DimensionPolicy) {
+        self.image_options.dimension_policy = policy;
+    }
+
+    /// Set how the Streamdeck is mounted.
+    ///
+    /// With [Rotation::Upside180] the images are rotated and the button ids are
+    /// remapped, so that button 0 is still the top left button as seen by the user.
+    /// This applies to uploaded images as well as to reported button events.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{Rotation, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let mut device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     device.set_panel_rotation(Rotation::Upside180);
+    /// }
+    /// ```
+    pub fn set_panel_rotation(&mut self, rotation: Rotation) {
+        self.panel_rotation = rotation;
+        self.clear_framebuffer();
+    }
+
+    /// Remember the image uploaded to a button.
+    fn store_in_framebuffer(&self, button_id: u8, image: &RgbImage) {
+        if let Some(entry) = self.framebuffer.lock().unwrap().get_mut(button_id as usize) {
+            *entry = Some(image.clone());
+        }
+        if let Some(hash) = self
+            .framebuffer_hashes
+            .lock()
+            .unwrap()
+            .get_mut(button_id as usize)
+        {
+            *hash = match self.cache_hash_mode {
+                CacheHashMode::Full => None,
+                CacheHashMode::Fast => Some(fast_image_hash(image)),
+            };
+        }
+    }
+
+    /// Forget the remembered image of one button, because it is not known as RgbImage.
+    fn forget_in_framebuffer(&self, button_id: usize) {
+        if let Some(entry) = self.framebuffer.lock().unwrap().get_mut(button_id) {
+            *entry = None;
+        }
+        if let Some(hash) = self.framebuffer_hashes.lock().unwrap().get_mut(button_id) {
+            *hash = None;
+        }
+    }
+
+    /// Forget all remembered button images, because the display content is unknown.
+    fn clear_framebuffer(&self) {
+        self.framebuffer.lock().unwrap().fill(None);
+        self.framebuffer_hashes.lock().unwrap().fill(None);
+    }
+
+    /// Map between the button id seen by the user and the physical button id.
+    ///
+    /// The mapping is its own inverse, so it is used in both directions.
+    /// Ids outside of the button range are not changed.
+    fn map_button_id(&self, button_id: usize) -> usize {
+        let total_num_buttons = self.device_type.total_num_buttons();
+        match self.panel_rotation {
+            Rotation::Upside180 if button_id < total_num_buttons => {
+                total_num_buttons - 1 - button_id
+            }
+            _ => button_id,
+        }
+    }
+
+    /// Read one report from the device and return the button changes.
+    ///
+    /// This blocks until the device sends a report. Every button change in the
+    /// report is returned, for example pressing two buttons at the same time results
+    /// in two events. The returned list is empty, if the report did not change any
+    /// button state.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     for event in device.read_button_events().unwrap() {
+    ///         println!("Button {} changed to {:?}", event.button_id, event.state);
+    ///     }
+    /// }
+    /// ```
+    pub fn read_button_events(&self) -> Result<Vec<ButtonEvent>, Error> {
+        self.read_button_events_timeout(None)
+    }
+
+    /// The button states as last read from the device, indexed by button id.
+    ///
+    /// The states are kept in the device object, so after an error in one of the event
+    /// loops they still reflect the last successful read. Calling the event loop again
+    /// continues from these states, instead of assuming all buttons are up.
+    ///
+    /// A slice can not be returned, because the states are shared with the event loops,
+    /// so a copy is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{ButtonState, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let held = device
+    ///         .last_button_states()
+    ///         .iter()
+    ///         .filter(|state| **state == ButtonState::Down)
+    ///         .count();
+    ///     println!("{} buttons are held", held);
+    /// }
+    /// ```
+    pub fn last_button_states(&self) -> Vec<ButtonState> {
+        let button_state = self.button_state.lock().unwrap();
+        (0..button_state.len())
+            .map(|button_id| button_state[self.map_button_id(button_id)])
+            .collect()
+    }
+
+    /// Read and discard all input for `duration`, e.g. during a modal operation.
+    ///
+    /// The button states are still updated from the discarded reports, so no events
+    /// for the suppressed presses appear afterwards. A button pressed during the
+    /// suppression and released after it produces no [ButtonState::Up] event either.
+    /// Do not call this while an event loop runs in another thread, it would receive
+    /// some of the reports.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // Ignore presses while the confirmation animation plays
+    ///     device.suppress_input(Duration::from_millis(10)).unwrap();
+    /// }
+    /// ```
+    pub fn suppress_input(&self, duration: Duration) -> Result<(), Error> {
+        let end = Instant::now() + duration;
+        loop {
+            let remaining = end.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            // A timeout of 0 ms would not wait, and the loop would spin until the end
+            let timeout = remaining.max(Duration::from_millis(1));
+            // Suppressed presses are not flashed
+            if let Some(states) = self.read_button_states(Some(timeout))? {
+                let mut suppressed_presses = self.suppressed_presses.lock().unwrap();
+                for event in self.update_button_states(states) {
+                    suppressed_presses[event.button_id as usize] = event.state == ButtonState::Down;
+                }
+            }
+        }
+    }
+
+    /// The accumulated position of an encoder of the Streamdeck Plus.
+    ///
+    /// Every turn of the encoder, as read by the event loops (or any other method reading
+    /// input), adds its steps to the position: clockwise positive, counterclockwise
+    /// negative. The position starts at 0 when opening the device.
+    /// Returns 0 for encoders the device does not have.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     // In another thread, the event loop is running
+    ///     println!("Volume: {}", device.encoder_position(0));
+    /// }
+    /// ```
+    pub fn encoder_position(&self, encoder: usize) -> i32 {
+        self.encoder_positions
+            .lock()
+            .unwrap()
+            .get(encoder)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Set the accumulated position of an encoder back to 0.
+    ///
+    /// See [StreamDeckDevice::encoder_position].
+    pub fn reset_encoder_position(&self, encoder: usize) {
+        if let Some(position) = self.encoder_positions.lock().unwrap().get_mut(encoder) {
+            *position = 0;
+        }
+    }
+
+    /// Add the turns of an encoder report of the Streamdeck Plus to the encoder positions.
+    ///
+    /// Reports of pressing the encoders are ignored.
+    fn update_encoder_positions(&self, report: &[u8]) {
+        if report.get(4) != Some(&PLUS_ENCODER_TURN) {
+            return;
+        }
+        let mut positions = self.encoder_positions.lock().unwrap();
+        for (position, delta) in positions.iter_mut().zip(report.iter().skip(5)) {
+            *position += *delta as i8 as i32;
+        }
+    }
+
+    /// Like [StreamDeckDevice::read_button_events], but waits at most `timeout` if given.
+    fn read_button_events_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<ButtonEvent>, Error> {
+        match self.read_button_states(timeout)? {
+            Some(states) => Ok(self.apply_button_states(states)),
+            // Encoder and touch input is not reported as button events
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Store the state of the (physical) buttons and return the changes as events.
+    ///
+    /// All paths reading input apply the states here, so the press flash (see
+    /// [StreamDeckDevice::set_press_flash]) follows every change.
+    fn apply_button_states(&self, states: Vec<ButtonState>) -> Vec<ButtonEvent> {
+        let mut result = self.update_button_states(states);
+        // Discard the releases of presses discarded by suppress_input
+        let mut suppressed_presses = self.suppressed_presses.lock().unwrap();
+        result.retain(|event| !std::mem::take(&mut suppressed_presses[event.button_id as usize]));
+        drop(suppressed_presses);
+        self.flash_pressed_buttons(&result);
+        result
+    }
+
+    /// Store the state of the (physical) buttons and return the changes as events,
+    /// without the press flash.
+    fn update_button_states(&self, states: Vec<ButtonState>) -> Vec<ButtonEvent> {
+        let mut result = Vec::new();
+        let mut button_state = self.button_state.lock().unwrap();
+        for (button_id, state) in states.into_iter().enumerate() {
+            if button_state[button_id] != state {
+                button_state[button_id] = state;
+                result.push(ButtonEvent {
+                    button_id: self.map_button_id(button_id) as u32,
+                    state,
+                });
+            }
+        }
+        result
+    }
+
+    /// Check whether a report from the device is ready, waiting at most `timeout_ms`.
+    ///
+    /// Use this before a blocking read (e.g. [StreamDeckDevice::read_button_events]),
+    /// to only commit to it when input is there. A `timeout_ms` of 0 does not wait,
+    /// -1 waits until a report arrives.
+    ///
+    /// **This consumes the report**, as hidapi can not look at a report without reading it.
+    /// The report is applied to the device state: the button states (see
+    /// [StreamDeckDevice::last_button_states]) and the encoder positions of the
+    /// Streamdeck Plus are updated. The button events contained in it are not delivered
+    /// to any later read, compare [StreamDeckDevice::last_button_states] before and
+    /// after if you need them.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     if device.input_available(0).unwrap() {
+    ///         println!("Buttons: {:?}", device.last_button_states());
+    ///     }
+    /// }
+    /// ```
+    pub fn input_available(&self, timeout_ms: i32) -> Result<bool, Error> {
+        let mut inbuffer = vec![0; self.report_length()];
+        let read = self
+            .hid_device
+            .read_timeout(&mut inbuffer, timeout_ms)
+            .map_err(|error| self.hid_error(error))?;
+        if read == 0 {
+            return Ok(false);
+        }
+        inbuffer.truncate(read);
+        debug!("Streamdeck read: {:?}", inbuffer);
+        if let Some(states) = self.button_states_from_report(&inbuffer) {
+            self.apply_button_states(states);
+        }
+        Ok(true)
+    }
+
+    /// Read one report from the device into caller owned buffers, without allocating.
+    ///
+    /// The report is read into `buf`, which should hold at least
+    /// [StreamDeckType::total_num_buttons] plus a few header bytes. `state` holds the known
+    /// state of the (physical) buttons and is updated by this function, it should start
+    /// with all buttons [ButtonState::Up]. Up to `events.len()` button changes are
+    /// written to `events` and their number is returned. Changes not fitting into `events`
+    /// are not recorded in `state`, so they are reported by the next call.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{ButtonEvent, ButtonState, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     let mut buf = [0u8; 64];
+    ///     let mut state = [ButtonState::Up; 32];
+    ///     let mut events = [ButtonEvent { button_id: 0, state: ButtonState::Up }; 8];
+    ///     let count = device.read_events_into(&mut events, &mut state, &mut buf).unwrap();
+    ///     for event in &events[..count] {
+    ///         println!("Button {} changed to {:?}", event.button_id, event.state);
+    ///     }
+    /// }
+    /// ```
+    pub fn read_events_into(
+        &self,
+        events: &mut [ButtonEvent],
+        state: &mut [ButtonState],
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let read = self
+            .hid_device
+            .read(buf)
+            .map_err(|error| self.hid_error(error))?;
+        let report = &buf[..read];
+        if self.device_type == StreamDeckType::Plus
+            && PlusInputKind::classify(report) != Some(PlusInputKind::Buttons)
+        {
+            return Ok(0);
+        }
+        let mut count = 0;
+        for (button_id, value) in self.device_type.strip_report_id(report).iter().enumerate() {
+            if count == events.len() || button_id == state.len() {
+                break;
+            }
+            let new_state = if *value == 0 {
+                ButtonState::Up
+            } else {
+                ButtonState::Down
+            };
+            if state[button_id] != new_state {
+                state[button_id] = new_state;
+                events[count] = ButtonEvent {
+                    button_id: self.map_button_id(button_id) as u32,
+                    state: new_state,
+                };
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Read the state of all buttons as a bitmask.
+    ///
+    /// Bit `i` is set, if button `i` is down. This reads one report from the device
+    /// (on the Streamdeck Plus, reports not containing button states are skipped)
+    /// and does not generate button events.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     println!("Buttons down: {:#b}", device.button_state_bitmask().unwrap());
+    /// }
+    /// ```
+    pub fn button_state_bitmask(&self) -> Result<u64, Error> {
+        loop {
+            if let Some(states) = self.read_button_states(None)? {
+                let mut result = 0u64;
+                for (button_id, state) in states.into_iter().enumerate() {
+                    if state == ButtonState::Down {
+                        result |= 1 << self.map_button_id(button_id);
+                    }
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Read one report from the device and return the state of the (physical) buttons.
+    ///
+    /// Returns `None` if the report does not contain button states.
+    /// If the device returned a short report, only the states of the buttons contained
+    /// in it are returned.
+    ///
+    /// With a `timeout`, this waits at most that long and returns no button states
+    /// if nothing was read.
+    fn read_button_states(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<ButtonState>>, Error> {
+        let mut inbuffer = vec![0; self.report_length()];
+        let read = match timeout {
+            Some(timeout) => self.hid_device.read_timeout(
+                &mut inbuffer,
+                timeout.as_millis().min(i32::MAX as u128) as i32,
+            ),
+            None => self.hid_device.read(&mut inbuffer),
+        }
+        .map_err(|error| self.hid_error(error))?;
+        // Do not interpret the unread tail as released buttons
+        inbuffer.truncate(read);
+        debug!("Streamdeck read: {:?}", inbuffer);
+        Ok(self.button_states_from_report(&inbuffer))
+    }
+
+    /// The length of an input report containing the state of all buttons.
+    fn report_length(&self) -> usize {
+        self.device_type.button_read_offset() + self.device_type.total_num_buttons()
+    }
+
+    /// Interpret a report read from the device and return the state of the (physical) buttons.
+    ///
+    /// Returns `None` if the report does not contain button states. Encoder reports of the
+    /// Streamdeck Plus are applied to the encoder positions.
+    fn button_states_from_report(&self, report: &[u8]) -> Option<Vec<ButtonState>> {
+        if self.device_type == StreamDeckType::Plus {
+            match PlusInputKind::classify(report) {
+                Some(PlusInputKind::Buttons) => {}
+                Some(PlusInputKind::Encoders) => {
+                    self.update_encoder_positions(report);
+                    return None;
+                }
+                _ => return None,
+            }
+        }
+        Some(
+            self.device_type
+                .strip_report_id(report)
+                .iter()
+                .map(|value| {
+                    if *value == 0 {
+                        ButtonState::Up
+                    } else {
+                        ButtonState::Down
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Create the image packages for a button, using the options of this device.
+    fn image_packages(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+        scratch: &mut Vec<u8>,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        image_packages_into(
+            self.device_type.clone(),
+            &self.rotate_for_panel(image),
+            self.map_button_id(button_id as usize) as u8,
+            &self.image_options,
+            scratch,
+        )
+    }
+
+    /// Like [StreamDeckDevice::image_packages], but the packages are created while iterating.
+    fn image_packages_iter(
+        &self,
+        button_id: u8,
+        image: &RgbImage,
+    ) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+        image_packages_iter(
+            self.device_type.clone(),
+            &self.rotate_for_panel(image),
+            self.map_button_id(button_id as usize) as u8,
+            &self.image_options,
+        )
+    }
+
+    /// Rotate an image, depending on how the panel is mounted.
+    fn rotate_for_panel<'a>(&self, image: &'a RgbImage) -> Cow<'a, RgbImage> {
+        self.panel_rotation.apply(image)
+    }
+
+    /// Write image packages to the device, checking that they are completely written.
+    ///
+    /// `button_id` is only used for error reporting and is `None` for the info screen.
+    fn write_image_packages<I>(&self, button_id: Option<u8>, image_packages: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<Vec<u8>, Error>>,
+    {
+        let _permit = self
+            .upload_governor
+            .as_ref()
+            .map(|governor| governor.acquire());
+        let mut packets = 0;
+        for (packet_index, image_package) in image_packages.into_iter().enumerate() {
+            packets = packet_index + 1;
+            let image_package = image_package?;
+            if packet_index > 0 && !self.interpacket_delay.is_zero() {
+                std::thread::sleep(self.interpacket_delay);
+            }
+            let image_package_len = image_package.len();
+            let result = self.write(&image_package).map_err(|source| {
+                self.in_context(Error::WriteFailed {
+                    button_id,
+                    packet_index,
+                    source,
+                })
+            })?;
+            if result != image_package_len {
+                return Err(Error::IncorrectWriteLengthError);
+            }
+        }
+        match button_id {
+            Some(button_id) => debug!(
+                "Uploaded image in {} packets to button {} of {}",
+                packets,
+                button_id,
+                self.log_context()
+            ),
+            None => debug!(
+                "Uploaded image in {} packets to the info screen of {}",
+                packets,
+                self.log_context()
+            ),
+        }
+        Ok(())
+    }
+
+    /// Write to the device, or only log the data in dry run mode.
+    fn write(&self, data: &[u8]) -> hidapi::HidResult<usize> {
+        if self.input_only {
+            return Err(input_only_error());
+        }
+        if self.dry_run {
+            debug!("Streamdeck dry run write: {}", hex_dump(data));
+            return Ok(data.len());
+        }
+        self.hid_device.write(data)
+    }
+
+    /// Send a feature report to the device, or only log it in dry run mode.
+    fn send_feature_report(&self, data: &[u8]) -> hidapi::HidResult<()> {
+        if self.input_only {
+            return Err(input_only_error());
+        }
+        if self.dry_run {
+            debug!("Streamdeck dry run feature report: {}", hex_dump(data));
+            return Ok(());
+        }
+        self.hid_device.send_feature_report(data)
+    }
+
+    /// Wait for button events!
+    ///
+    /// The Idea is, that this runs in its own thread waiting for events on the device
+    /// and calling the closure when an event occurs.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     // device.on_button_events(|event| {
+    ///     //    println!("Button {} changed to {:?}", event.button_id, event.state)
+    ///     // }).unwrap();
+    /// }
+    ///
+    pub fn on_button_events<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: Fn(ButtonEvent),
+    {
+        self.run_button_events(cb, || true)
+    }
+
+    /// Wait for input events of any kind: buttons, encoders and the touchscreen.
+    ///
+    /// Works like [StreamDeckDevice::on_button_events], but all input the device reports
+    /// is decoded. Devices with only buttons only produce [InputEvent::Button], the
+    /// Streamdeck Plus also produces [InputEvent::Encoder] and [InputEvent::Touch].
+    /// Handling [InputEvent] makes code ready for devices with other input elements.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::{InputEvent, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     // device.on_input_events(|event| match event {
+    ///     //     InputEvent::Button(event) => println!("Button {:?}", event),
+    ///     //     InputEvent::Encoder(event) => println!("Encoder {:?}", event),
+    ///     //     InputEvent::Touch(event) => println!("Touch {:?}", event),
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn on_input_events<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: Fn(InputEvent),
+    {
+        let mut inbuffer = vec![0; INPUT_REPORT_BUFFER_SIZE.max(self.report_length())];
+        loop {
+            let read = self
+                .hid_device
+                .read(&mut inbuffer)
+                .map_err(|error| self.hid_error(error))?;
+            let report = &inbuffer[..read];
+            debug!("Streamdeck read: {:?}", report);
+            for event in self.input_events_from_report(report) {
+                cb(event);
+            }
+        }
+    }
+
+    /// Decode a report read from the device into input events.
+    fn input_events_from_report(&self, report: &[u8]) -> Vec<InputEvent> {
+        let kind = if self.device_type == StreamDeckType::Plus {
+            PlusInputKind::classify(report)
+        } else {
+            Some(PlusInputKind::Buttons)
+        };
+        match kind {
+            Some(PlusInputKind::Buttons) => {
+                // Ignore the unused tail of the buffer
+                let report = &report[..report.len().min(self.report_length())];
+                self.button_states_from_report(report)
+                    .map(|states| self.apply_button_states(states))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(InputEvent::Button)
+                    .collect()
+            }
+            Some(PlusInputKind::Encoders) => self
+                .encoder_events_from_report(report)
+                .into_iter()
+                .map(InputEvent::Encoder)
+                .collect(),
+            Some(PlusInputKind::Touch) => touch_event_from_report(report)
+                .map(InputEvent::Touch)
+                .into_iter()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Decode an encoder report of the Streamdeck Plus and update the encoder state.
+    fn encoder_events_from_report(&self, report: &[u8]) -> Vec<EncoderEvent> {
+        let values = report.iter().skip(5).take(NUM_PLUS_ENCODERS).enumerate();
+        if report.get(4) == Some(&PLUS_ENCODER_TURN) {
+            self.update_encoder_positions(report);
+            return values
+                .filter(|(_, delta)| **delta != 0)
+                .map(|(encoder, delta)| EncoderEvent::Turn {
+                    encoder: encoder as u32,
+                    delta: *delta as i8 as i32,
+                })
+                .collect();
+        }
+        let mut encoder_states = self.encoder_states.lock().unwrap();
+        let mut result = Vec::new();
+        for (encoder, value) in values {
+            let state = if *value == 0 {
+                ButtonState::Up
+            } else {
+                ButtonState::Down
+            };
+            if encoder_states[encoder] != state {
+                encoder_states[encoder] = state;
+                result.push(EncoderEvent::Press {
+                    encoder: encoder as u32,
+                    state,
+                });
+            }
+        }
+        result
+    }
+
+    /// Wait for button events, with the buttons given by their position.
+    ///
+    /// Works like [StreamDeckDevice::on_button_events], but the events contain the row
+    /// and column of the button (see [StreamDeckType::button_coordinates]).
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     // device.on_button_grid_events(|event| {
+    ///     //    println!("Button at {}/{} changed to {:?}", event.row, event.col, event.state)
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn on_button_grid_events<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: Fn(GridButtonEvent),
+    {
+        self.on_button_events(|event| {
+            if let Some((row, col)) = self.device_type.button_coordinates(event.button_id) {
+                cb(GridButtonEvent {
+                    row,
+                    col,
+                    state: event.state,
+                });
+            }
+        })
+    }
+
+    /// Wait for button events, until told to stop.
+    ///
+    /// Works like [StreamDeckDevice::on_button_events], but `keep_running` is asked before
+    /// every read from the device. When it returns false, this function returns `Ok(())`,
+    /// so that a normal stop can be distinguished from an error.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///     let stop = AtomicBool::new(false);
+    ///
+    ///     // Stop after the first event
+    ///     // device.run_button_events(
+    ///     //     |event| {
+    ///     //         println!("Button {} changed to {:?}", event.button_id, event.state);
+    ///     //         stop.store(true, Ordering::Relaxed);
+    ///     //     },
+    ///     //     || !stop.load(Ordering::Relaxed),
+    ///     // ).unwrap();
+    /// }
+    /// ```
+    pub fn run_button_events<F, P>(&self, cb: F, keep_running: P) -> Result<(), Error>
+    where
+        F: Fn(ButtonEvent),
+        P: Fn() -> bool,
+    {
+        while keep_running() {
+            for event in self.read_button_events()? {
+                cb(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for button events and repeat the events of held buttons.
+    ///
+    /// Works like [StreamDeckDevice::on_button_events], but while a button is held down,
+    /// a [ButtonState::Down] event for it is repeated every `interval`, counted from the
+    /// time the button was pressed. This is useful for scrolling through lists by holding
+    /// a button.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     // device.on_button_events_repeat(Duration::from_millis(250), |event| {
+    ///     //    println!("Button {} is {:?}", event.button_id, event.state)
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn on_button_events_repeat<F>(&self, interval: Duration, cb: F) -> Result<(), Error>
+    where
+        F: Fn(ButtonEvent),
+    {
+        // The time of the next repeat of every held button
+        let start = Instant::now();
+        let mut next_repeats: Vec<Option<Instant>> = self
+            .last_button_states()
+            .into_iter()
+            .map(|state| (state == ButtonState::Down).then_some(start + interval))
+            .collect();
+        loop {
+            let timeout = next_repeats.iter().flatten().min().map(|next_repeat| {
+                // Round up to whole milliseconds, to not wake up before the repeat
+                let wait = next_repeat.saturating_duration_since(Instant::now());
+                Duration::from_millis(wait.as_micros().div_ceil(1000) as u64)
+            });
+            for event in self.read_button_events_timeout(timeout)? {
+                next_repeats[event.button_id as usize] = match event.state {
+                    ButtonState::Down => Some(Instant::now() + interval),
+                    ButtonState::Up => None,
+                };
+                cb(event);
+            }
+            let now = Instant::now();
+            for (button_id, next_repeat) in next_repeats.iter_mut().enumerate() {
+                if next_repeat.is_some_and(|next_repeat| now >= next_repeat) {
+                    cb(ButtonEvent {
+                        button_id: button_id as u32,
+                        state: ButtonState::Down,
+                    });
+                    *next_repeat = Some(now + interval);
+                }
+            }
+        }
+    }
+
+    /// Wait for button events and deliver them in frames.
+    ///
+    /// Works like [StreamDeckDevice::on_button_events], but all events from one report
+    /// of the device are passed to the closure at once. Reports without any changes do
+    /// not call the closure.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckDevice;
+    ///
+    /// fn main() {
+    ///     let hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///     let device = StreamDeckDevice::open_first_device(&hidapi).unwrap();
+    ///
+    ///     // device.on_button_event_frames(|events| {
+    ///     //    println!("{} buttons changed", events.len())
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn on_button_event_frames<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: Fn(&[ButtonEvent]),
+    {
+        loop {
+            let events = self.read_button_events()?;
+            if !events.is_empty() {
+                cb(&events);
+            }
+        }
+    }
+}
+
+impl<API: HidApiTrait> Drop for StreamDeckDevice<API> {
+    fn drop(&mut self) {
+        info!("Closed {}", self.log_context());
+    }
+}
+
+/// Pixels between the buttons in [StreamDeckDevice::render_panel_contact_sheet].
+pub const CONTACT_SHEET_GAP: u32 = 4;
+
+/// Color of the gaps in [StreamDeckDevice::render_panel_contact_sheet].
+const CONTACT_SHEET_GAP_COLOR: image::Rgb<u8> = image::Rgb([64, 64, 64]);
+
+/// Number of encoders of the Streamdeck Plus.
+const NUM_PLUS_ENCODERS: usize = 4;
+
+/// How long [StreamDeckDevice::heartbeat] waits for a report, in milliseconds.
+const HEARTBEAT_READ_TIMEOUT_MS: i32 = 100;
+
+/// Marks an encoder report of the Streamdeck Plus as turning, instead of pressing.
+const PLUS_ENCODER_TURN: u8 = 0x01;
+
+/// Size of the buffer for reading reports of any kind, see [StreamDeckDevice::on_input_events].
+const INPUT_REPORT_BUFFER_SIZE: usize = 64;
+
+/// Number of reports read by [StreamDeckDevice::self_test].
+const SELF_TEST_READS: usize = 3;
+
+/// How long [StreamDeckDevice::self_test] waits for each report.
+const SELF_TEST_READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Number of brightness steps when fading in [StreamDeckDevice::transition_to].
+const TRANSITION_FADE_STEPS: u8 = 10;
+
+/// Parts of hidapi error messages, for which opening a device is retried.
+///
+/// The platforms report a device held by another program as busy (Linux),
+/// exclusive access (macOS) or a sharing violation (Windows).
+const TRANSIENT_OPEN_ERRORS: [&str; 6] = [
+    "busy",
+    "exclusive",
+    "sharing violation",
+    "temporarily",
+    "try again",
+    "timed out",
+];
+
+/// Decode a touch report of the Streamdeck Plus.
+///
+/// Returns `None` for reports too short or of an unknown touch kind.
+fn touch_event_from_report(report: &[u8]) -> Option<TouchEvent> {
+    let coordinate = |index: usize| -> Option<u16> {
+        Some(u16::from_le_bytes([
+            *report.get(index)?,
+            *report.get(index + 1)?,
+        ]))
+    };
+    let (x, y) = (coordinate(6)?, coordinate(8)?);
+    match report.get(4)? {
+        0x01 => Some(TouchEvent::Short { x, y }),
+        0x02 => Some(TouchEvent::Long { x, y }),
+        0x03 => Some(TouchEvent::Drag {
+            x,
+            y,
+            x_out: coordinate(10)?,
+            y_out: coordinate(12)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Shuffle the values into a random order (Fisher-Yates).
+fn shuffle(values: &mut [usize]) {
+    use std::hash::{BuildHasher, Hasher};
+    // Seed a xorshift generator from the random keys of the std hash map
+    let mut state = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+        | 1;
+    for index in (1..values.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        values.swap(index, (state % (index as u64 + 1)) as usize);
+    }
+}
+
+/// The USB port numbers of a hid device, used to order devices by the port they are plugged in.
+///
+/// For the libusb backend the path contains the USB topology (`bus-port.port:config.interface`),
+/// so the numbers are the bus and port numbers. For the hidraw backend on linux
+/// (`/dev/hidrawN`), the topology is read from sysfs.
+///
+/// Other paths do not contain the topology: on macOS and Windows the numbers are those
+/// of the registry or device interface, and hidraw devices not found in sysfs give the
+/// enumeration number. Ordering by them is deterministic, but it is not tied to the
+/// physical ports and can change when replugging the devices or rebooting.
+///
+/// # Example
+/// ```
+/// use streamdeck_hid_rs::port_path;
+///
+/// assert_eq!(port_path("1-2.3:1.0"), vec![1, 2, 3, 1, 0]);
+/// ```
+pub fn port_path(path: &str) -> Vec<u64> {
+    let topology = hidraw_usb_interface(path);
+    path_numbers(topology.as_deref().unwrap_or(path))
+}
+
+/// The numbers in a path, in their order.
+fn path_numbers(path: &str) -> Vec<u64> {
+    path.split(|c: char| !c.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().unwrap_or(u64::MAX))
+        .collect()
+}
+
+/// The USB interface (`bus-port.port:config.interface`) of a linux hidraw device, from sysfs.
+fn hidraw_usb_interface(path: &str) -> Option<String> {
+    let name = path.strip_prefix("/dev/")?;
+    if !name.starts_with("hidraw") {
+        return None;
+    }
+    let device = std::fs::canonicalize(format!("/sys/class/hidraw/{}/device", name)).ok()?;
+    usb_interface_of_sysfs_device(&device.to_string_lossy())
+}
+
+/// Find the USB interface in the sysfs path of a hid device.
+///
+/// The path of a hid device is below its USB interface, like
+/// `/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.3/1-2.3:1.0/0003:0FD9:006C.0005`.
+fn usb_interface_of_sysfs_device(device: &str) -> Option<String> {
+    device
+        .rsplit('/')
+        .find(|component| {
+            component.contains('-')
+                && component.contains(':')
+                && component
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, '-' | '.' | ':'))
+        })
+        .map(String::from)
+}
+
+/// Check if opening a device failed for a reason, that may go away by trying again.
+fn is_transient_open_error(error: &hidapi::HidError) -> bool {
+    match error {
+        hidapi::HidError::IoError { error } => matches!(
+            error.kind(),
+            std::io::ErrorKind::ResourceBusy
+                | std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::Interrupted
+        ),
+        hidapi::HidError::HidApiError { message } => {
+            let message = message.to_lowercase();
+            TRANSIENT_OPEN_ERRORS
+                .iter()
+                .any(|transient| message.contains(transient))
+        }
+        _ => false,
+    }
+}
+
+/// The error for writing to a device opened with [StreamDeckDevice::open_input_only].
+fn input_only_error() -> hidapi::HidError {
+    hidapi::HidError::HidApiError {
+        message: String::from("the device has been opened for input only"),
+    }
+}
+
+/// Format bytes as space separated hex values for logging.
+fn hex_dump(data: &[u8]) -> String {
+    data.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Set the brightness of multiple devices.
+///
+/// Returns the result for every device, in the same order as `devices`, so that
+/// partial failures can be handled.
+///
+/// # Example
+/// ```
+/// use streamdeck_hid_rs::{set_brightness_all, StreamDeckDevice};
+///
+/// fn main() {
+///     let hidapi = hidapi::HidApi::new().unwrap();
+///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+///     let devices = vec![StreamDeckDevice::open_first_device(&hidapi).unwrap()];
+///     for result in set_brightness_all(&devices, 50) {
+///         result.unwrap();
+///     }
+/// }
+/// ```
+pub fn set_brightness_all<API: HidApiTrait>(
+    devices: &[StreamDeckDevice<API>],
+    brightness: u8,
+) -> Vec<Result<(), Error>> {
+    devices
+        .iter()
+        .map(|device| device.set_brightness(brightness))
+        .collect()
+}
+
+/// Reset communication with multiple devices.
+///
+/// Returns the result for every device, in the same order as `devices`, so that
+/// partial failures can be handled.
+pub fn reset_all<API: HidApiTrait>(devices: &[StreamDeckDevice<API>]) -> Vec<Result<(), Error>> {
+    devices.iter().map(|device| device.reset()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::Error::HidError;
+    #[allow(unused_imports)]
+    use mockall::predicate::*;
+    #[allow(unused_imports)]
+    use mockall::*;
+
+    /// Create a device of the given type, whose writes all succeed.
+    fn mock_device(device_type: StreamDeckType) -> StreamDeckDevice<MockMockHidApi> {
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_send_feature_report()
+            .returning(|_data: &[u8]| Ok(()));
+        hid_device
+            .expect_write()
+            .returning(|data: &[u8]| Ok(data.len()));
+        StreamDeckDevice::new(device_type, hid_device)
+    }
+
+    #[test]
+    fn test_from_hid_device() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_send_feature_report()
+            .with(eq(StreamDeckType::Mini.brightness_packet(40)))
+            .times(1)
+            .returning(|_data: &[u8]| Ok(()));
+
+        // Act
+        let device =
+            StreamDeckDevice::<MockMockHidApi>::from_hid_device(hid_device, StreamDeckType::Mini);
+        let result = device.set_brightness(40);
+
+        // Test
+        assert!(result.is_ok());
+        assert_eq!(device.device_type, StreamDeckType::Mini);
+    }
+
+    #[test]
+    fn test_hid_device() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_write()
+            .with(eq(vec![0x02, 0x01]))
+            .times(1)
+            .returning(|data: &[u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+
+        // Act
+        let hid_device: &MockHidDeviceTrait = device.hid_device();
+        let result = hid_device.write(&[0x02, 0x01]);
+
+        // Test
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    /// Logger collecting all messages, to test what is logged.
+    struct TestLogger {
+        messages: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.messages
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static TEST_LOGGER: TestLogger = TestLogger {
+        messages: std::sync::Mutex::new(Vec::new()),
+    };
+
+    /// Install the [TEST_LOGGER], can be called by multiple tests.
+    fn test_logger() -> &'static TestLogger {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&TEST_LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+        &TEST_LOGGER
+    }
+
+    #[test]
+    fn test_log_set_brightness() {
+        // Setup
+        let logger = test_logger();
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_send_feature_report()
+            .returning(|_data: &[u8]| Ok(()));
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Plus, hid_device);
+        device.serial_number = Some(String::from("LOG-TEST"));
+
+        // Act
+        device.set_brightness(33).unwrap();
+
+        // Test
+        assert!(logger
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|message| message == "Set brightness of Streamdeck Plus (LOG-TEST) to 33"));
+    }
+
+    #[test]
+    fn test_set_dry_run() {
+        // Setup
+        let logger = test_logger();
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device.expect_write().never();
+        hid_device.expect_send_feature_report().never();
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        device.set_dry_run(true);
+        device.set_brightness(0x2a).unwrap();
+        device.clear_all_buttons().unwrap();
+
+        // Test
+        let messages = logger.messages.lock().unwrap();
+        assert!(messages
+            .iter()
+            .any(|message| message == "Streamdeck dry run feature report: 05 55 aa d1 01 2a 00 00 00 00 00 00 00 00 00 00 00"));
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| message.starts_with("Streamdeck dry run write: 02 01"))
+                .count(),
+            StreamDeckType::Mini.typical_packet_count() * StreamDeckType::Mini.total_num_buttons()
+        );
+    }
+
+    #[test]
+    fn test_list_devices_with_capabilities() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            let mut info_mock = MockDeviceInfoTrait::new();
+            info_mock
+                .expect_vendor_id()
+                .returning(|| StreamDeckType::Xl.get_vendor_id());
+            info_mock
+                .expect_product_id()
+                .returning(|| StreamDeckType::Xl.get_product_id());
+            Vec::from([info_mock])
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_devices_with_capabilities(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 1);
+        let capabilities = &devices[0].0;
+        assert_eq!(capabilities.device_type, StreamDeckType::Xl);
+        assert_eq!(capabilities.num_buttons, (4, 8));
+        assert_eq!(capabilities.image_spec.size, (96, 96));
+        assert_eq!(capabilities.info_image_size, None);
+    }
+
+    #[test]
+    fn test_describe_devices() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            let mut info_mock = MockDeviceInfoTrait::new();
+            info_mock
+                .expect_vendor_id()
+                .returning(|| StreamDeckType::Mini.get_vendor_id());
+            info_mock
+                .expect_product_id()
+                .returning(|| StreamDeckType::Mini.get_product_id());
+            info_mock
+                .expect_manufacturer_string()
+                .returning(|| Some(String::from("Elgato Systems")));
+            info_mock
+                .expect_product_string()
+                .returning(|| Some(String::from("Stream Deck Mini")));
+            info_mock
+                .expect_bus_type()
+                .returning(|| hidapi::BusType::Bluetooth);
+            Vec::from([info_mock])
+        });
+
+        // Act
+        let devices = StreamDeckDevice::describe_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].device_type, StreamDeckType::Mini);
+        assert_eq!(devices[0].manufacturer.as_deref(), Some("Elgato Systems"));
+        assert_eq!(devices[0].product.as_deref(), Some("Stream Deck Mini"));
+        assert!(matches!(devices[0].bus_type, hidapi::BusType::Bluetooth));
+    }
+
+    #[test]
+    fn test_list_devices_empty() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(Vec::new);
+
+        // Act
+        let devices = StreamDeckDevice::list_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 0);
+    }
+
+    #[test]
+    fn test_list_devices_non_streamdeck_empty() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            let mut info_mock = MockDeviceInfoTrait::new();
+            info_mock.expect_vendor_id().returning(|| 1);
+            info_mock.expect_product_id().returning(|| 1);
+            Vec::from([info_mock])
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 0);
+    }
+
+    #[test]
+    fn test_list_devices_streamdeck_device() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            let mut wrong_info_mock = MockDeviceInfoTrait::new();
+            wrong_info_mock.expect_vendor_id().returning(|| 1);
+            wrong_info_mock.expect_product_id().returning(|| 1);
+            let mut correct_info_mock = MockDeviceInfoTrait::new();
+            correct_info_mock
+                .expect_vendor_id()
+                .returning(|| StreamDeckType::Xl.get_vendor_id());
+            correct_info_mock
+                .expect_product_id()
+                .returning(|| StreamDeckType::Xl.get_product_id());
+            Vec::from([wrong_info_mock, correct_info_mock])
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].0, StreamDeckType::Xl);
+    }
+
+    #[test]
+    fn test_list_drivable_devices() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            // The Stream Deck Pedal and an XL
+            [
+                (0x0fd9, 0x86),
+                (0x0fd9, StreamDeckType::Xl.get_product_id()),
+            ]
+            .into_iter()
+            .map(|(vendor_id, product_id)| {
+                let mut info_mock = MockDeviceInfoTrait::new();
+                info_mock.expect_vendor_id().returning(move || vendor_id);
+                info_mock.expect_product_id().returning(move || product_id);
+                info_mock
+            })
+            .collect()
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_drivable_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].0, StreamDeckType::Xl);
+    }
+
+    #[test]
+    fn test_list_devices_sorted_by_port() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            ["1-10:1.0", "1-2:1.0"]
+                .into_iter()
+                .map(|path| {
+                    let mut info_mock = MockDeviceInfoTrait::new();
+                    info_mock
+                        .expect_vendor_id()
+                        .returning(|| StreamDeckType::Mini.get_vendor_id());
+                    info_mock
+                        .expect_product_id()
+                        .returning(|| StreamDeckType::Mini.get_product_id());
+                    info_mock
+                        .expect_path()
+                        .returning(move || String::from(path));
+                    info_mock
+                })
+                .collect()
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_devices(&api_mock);
+
+        // Test
+        let paths: Vec<String> = devices.iter().map(|(_, info)| info.path()).collect();
+        assert_eq!(paths, vec!["1-2:1.0", "1-10:1.0"]);
+    }
+
+    #[test]
+    fn test_port_path() {
+        assert_eq!(port_path("1-2.3:1.0"), vec![1, 2, 3, 1, 0]);
+        assert!(port_path("1-2:1.0") < port_path("1-10:1.0"));
+        assert!(port_path("").is_empty());
+    }
+
+    #[test]
+    fn test_port_path_hidraw() {
+        // Setup (hidraw2 was enumerated first, but is plugged into the later port)
+        let hidraw2 = "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4/1-4:1.0/0003:0FD9:006C.0003";
+        let hidraw10 =
+            "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.3/1-2.3:1.0/0003:0FD9:0080.000B";
+
+        // Act
+        let hidraw2_interface = usb_interface_of_sysfs_device(hidraw2).unwrap();
+        let hidraw10_interface = usb_interface_of_sysfs_device(hidraw10).unwrap();
+
+        // Test
+        assert_eq!(hidraw2_interface, "1-4:1.0");
+        assert_eq!(hidraw10_interface, "1-2.3:1.0");
+        assert!(port_path(&hidraw10_interface) < port_path(&hidraw2_interface));
+        assert_eq!(
+            usb_interface_of_sysfs_device("/sys/devices/virtual/misc"),
+            None
+        );
+        assert_eq!(hidraw_usb_interface("1-2.3:1.0"), None);
+    }
+
+    #[test]
+    fn test_list_unique_devices_same_serial() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().times(1).returning(|| {
+            (0..2)
+                .rev()
+                .map(|interface_number| {
+                    let mut info_mock = MockDeviceInfoTrait::new();
+                    info_mock
+                        .expect_vendor_id()
+                        .returning(|| StreamDeckType::Xl.get_vendor_id());
+                    info_mock
+                        .expect_product_id()
+                        .returning(|| StreamDeckType::Xl.get_product_id());
+                    info_mock
+                        .expect_serial_number()
+                        .returning(|| Some(String::from("CL12345")));
+                    info_mock
+                        .expect_interface_number()
+                        .returning(move || interface_number);
+                    info_mock
+                        .expect_path()
+                        .returning(move || format!("1-4:1.{}", interface_number));
+                    info_mock
+                })
+                .collect()
+        });
+
+        // Act
+        let devices = StreamDeckDevice::list_unique_devices(&api_mock);
+
+        // Test
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].0, StreamDeckType::Xl);
+        assert_eq!(devices[0].1.interface_number(), 0);
+    }
+
+    #[test]
+    fn test_open_with_retry() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        let mut sequence = Sequence::new();
+        api_mock
+            .expect_open_path()
+            .with(eq("/dev/hidraw1"))
+            .times(2)
+            .in_sequence(&mut sequence)
+            .returning(|_path: &str| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device busy"),
+                })
+            });
+        api_mock
+            .expect_open_path()
+            .with(eq("/dev/hidraw1"))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_path: &str| Ok(MockHidDeviceTrait::new()));
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock
+            .expect_vendor_id()
+            .returning(|| StreamDeckType::Orig.get_vendor_id());
+        info_mock
+            .expect_product_id()
+            .returning(|| StreamDeckType::Orig.get_product_id());
+        info_mock
+            .expect_path()
+            .returning(|| String::from("/dev/hidraw1"));
+        info_mock.expect_serial_number().returning(|| None);
+
+        // Act
+        let device = StreamDeckDevice::open_with_retry(&api_mock, &info_mock, 3, Duration::ZERO);
+
+        // Test
+        assert_eq!(device.unwrap().device_type, StreamDeckType::Orig);
+    }
+
+    #[test]
+    fn test_open_input_only() {
+        // Setup (the device mock fails on any write or feature report)
+        let mut api_mock = MockMockHidApi::new();
+        api_mock
+            .expect_open_path()
+            .with(eq("/dev/hidraw1"))
+            .times(1)
+            .returning(|_path: &str| Ok(MockHidDeviceTrait::new()));
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock
+            .expect_vendor_id()
+            .returning(|| StreamDeckType::Mini.get_vendor_id());
+        info_mock
+            .expect_product_id()
+            .returning(|| StreamDeckType::Mini.get_product_id());
+        info_mock
+            .expect_path()
+            .returning(|| String::from("/dev/hidraw1"));
+        info_mock
+            .expect_serial_number()
+            .returning(|| Some(String::from("CL12345")));
+
+        // Act
+        let device = StreamDeckDevice::open_input_only(&api_mock, &info_mock).unwrap();
+
+        // Test
+        assert_eq!(device.serial_number.as_deref(), Some("CL12345"));
+        assert!(matches!(device.set_brightness(50), Err(Error::HidError(_))));
+        assert!(matches!(
+            device.set_button_image(0, &RgbImage::new(80, 80)),
+            Err(Error::WriteFailed { .. })
+        ));
+        assert!(device.reset().is_err());
+    }
+
+    #[test]
+    fn test_open_with_retry_exhausted() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock
+            .expect_open_path()
+            .times(2)
+            .returning(|_path: &str| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device busy"),
+                })
+            });
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock
+            .expect_vendor_id()
+            .returning(|| StreamDeckType::Orig.get_vendor_id());
+        info_mock
+            .expect_product_id()
+            .returning(|| StreamDeckType::Orig.get_product_id());
+        info_mock
+            .expect_path()
+            .returning(|| String::from("/dev/hidraw0"));
+
+        // Act
+        let device = StreamDeckDevice::open_with_retry(&api_mock, &info_mock, 2, Duration::ZERO);
+
+        // Test
+        match device {
+            Err(Error::Busy {
+                source: hidapi::HidError::HidApiError { message },
+            }) => assert_eq!(message, "device busy"),
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_open_with_retry_permanent_error() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock
+            .expect_open_path()
+            .times(1)
+            .returning(|_path: &str| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("Permission denied"),
+                })
+            });
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock
+            .expect_vendor_id()
+            .returning(|| StreamDeckType::Orig.get_vendor_id());
+        info_mock
+            .expect_product_id()
+            .returning(|| StreamDeckType::Orig.get_product_id());
+        info_mock
+            .expect_path()
+            .returning(|| String::from("/dev/hidraw0"));
+
+        // Act
+        let device = StreamDeckDevice::open_with_retry(&api_mock, &info_mock, 5, Duration::ZERO);
+
+        // Test
+        assert!(matches!(device, Err(Error::HidError(_))));
+    }
+
+    #[test]
+    fn test_open_first_of_type() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_device_list().returning(|| {
+            [StreamDeckType::Xl, StreamDeckType::Mini]
+                .into_iter()
+                .enumerate()
+                .map(|(index, device_type)| {
+                    let mut info_mock = MockDeviceInfoTrait::new();
+                    let vendor_id = device_type.get_vendor_id();
+                    let product_id = device_type.get_product_id();
+                    info_mock.expect_vendor_id().returning(move || vendor_id);
+                    info_mock.expect_product_id().returning(move || product_id);
+                    info_mock.expect_serial_number().returning(|| None);
+                    info_mock
+                        .expect_path()
+                        .returning(move || format!("/dev/hidraw{}", index));
+                    info_mock
+                })
+                .collect()
+        });
+        api_mock
+            .expect_open_path()
+            .with(eq("/dev/hidraw1"))
+            .times(1)
+            .returning(|_path: &str| Ok(MockHidDeviceTrait::new()));
+
+        // Act
+        let device = StreamDeckDevice::open_first_of_type(&api_mock, StreamDeckType::Mini);
+        let missing = StreamDeckDevice::open_first_of_type(&api_mock, StreamDeckType::Orig);
+
+        // Test
+        assert_eq!(device.unwrap().device_type, StreamDeckType::Mini);
+        assert!(matches!(missing, Err(Error::NoDeviceFound)));
+    }
+
+    #[test]
+    fn test_open_registered_compatible() {
+        // Setup
+        StreamDeckType::register_compatible(0x1234, 0x0086, StreamDeckType::Xl);
+        let mut api_mock = MockMockHidApi::new();
+        api_mock.expect_open().never();
+        api_mock
+            .expect_open_path()
+            .with(eq("/dev/hidraw3"))
+            .times(1)
+            .returning(|_path: &str| Ok(MockHidDeviceTrait::new()));
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock.expect_vendor_id().returning(|| 0x1234);
+        info_mock.expect_product_id().returning(|| 0x0086);
+        info_mock
+            .expect_path()
+            .returning(|| String::from("/dev/hidraw3"));
+        info_mock.expect_serial_number().returning(|| None);
+
+        // Act
+        let device = StreamDeckDevice::open(&api_mock, &info_mock);
+
+        // Test
+        assert_eq!(device.unwrap().device_type, StreamDeckType::Xl);
+    }
+
+    #[test]
+    fn test_set_button_image_into_reuses_scratch() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let mut image = RgbImage::new(80, 80);
+        let mut scratch = Vec::new();
+
+        // Act
+        device
+            .set_button_image_into(0, &image, &mut scratch)
+            .unwrap();
+        image.put_pixel(3, 4, image::Rgb([255, 0, 0]));
+        device
+            .set_button_image_into(1, &image, &mut scratch)
+            .unwrap();
+
+        // Test
+        let mut expected =
+            crate::image::image_packages(StreamDeckType::Mini, &RgbImage::new(80, 80), 0).unwrap();
+        expected.extend(crate::image::image_packages(StreamDeckType::Mini, &image, 1).unwrap());
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_upload_governor_serializes_uploads() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let governor = std::sync::Arc::new(UploadGovernor::new(1));
+        let devices: Vec<_> = (0..2)
+            .map(|device_index| {
+                let mut hid_device = MockHidDeviceTrait::new();
+                let written_clone = written.clone();
+                hid_device.expect_write().returning(move |data: &[u8]| {
+                    written_clone.lock().unwrap().push(device_index);
+                    std::thread::sleep(Duration::from_millis(1));
+                    Ok(data.len())
+                });
+                let mut device =
+                    StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+                device.set_upload_governor(governor.clone());
+                device
+            })
+            .collect();
+
+        // Act
+        let threads: Vec<_> = devices
+            .into_iter()
+            .map(|device| {
+                std::thread::spawn(move || {
+                    device.set_button_image(0, &RgbImage::new(80, 80)).unwrap()
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Test
+        let written = written.lock().unwrap();
+        let switches = written.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        assert!(written.len() > 2);
+        assert_eq!(switches, 1);
+        assert_eq!(governor.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_estimate_full_refresh_bytes() {
+        // Setup
+        let mini = mock_device(StreamDeckType::Mini);
+        let xl = mock_device(StreamDeckType::Xl);
+        let mini_sample = RgbImage::new(80, 80);
+        let xl_sample = RgbImage::new(96, 96);
+
+        // Act
+        let mini_bytes = mini.estimate_full_refresh_bytes(&mini_sample).unwrap();
+        let xl_bytes = xl.estimate_full_refresh_bytes(&xl_sample).unwrap();
+
+        // Test
+        let mini_packets = crate::image::image_packages(StreamDeckType::Mini, &mini_sample, 0)
+            .unwrap()
+            .len();
+        let xl_packets = crate::image::image_packages(StreamDeckType::Xl, &xl_sample, 0)
+            .unwrap()
+            .len();
+        assert_eq!(mini_bytes, 6 * mini_packets * 8191);
+        assert_eq!(xl_bytes, 32 * xl_packets * 1024);
+        assert!(matches!(
+            mini.estimate_full_refresh_bytes(&xl_sample),
+            Err(Error::DimensionMismatch(80, 80))
+        ));
+    }
+
+    #[test]
+    fn test_set_interpacket_delay() {
+        // Setup
+        let written_at = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_at_clone = written_at.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_at_clone.lock().unwrap().push(Instant::now());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let delay = Duration::from_millis(20);
+
+        // Act
+        device.set_interpacket_delay(delay);
+        device.set_button_image(0, &RgbImage::new(72, 72)).unwrap();
+
+        // Test
+        let written_at = written_at.lock().unwrap();
+        assert_eq!(written_at.len(), 2);
+        assert!(written_at[1] - written_at[0] >= delay);
+    }
+
+    #[test]
+    fn test_set_button_image_named() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let image = RgbImage::new(80, 80);
+
+        // Act
+        let top_left = device.set_button_image_named("TopLeft", &image);
+        let unknown = device.set_button_image_named("Middle", &image);
+
+        // Test
+        assert!(top_left.is_ok());
+        assert!(matches!(unknown, Err(Error::UnknownButtonName(name)) if name == "Middle"));
+        let expected = crate::image::image_packages(StreamDeckType::Mini, &image, 0).unwrap();
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_button_image_within_budget() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        // Noise does not compress well
+        let mut state = 1u32;
+        let image = RgbImage::from_fn(96, 96, |_, _| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            image::Rgb([(state >> 24) as u8, (state >> 16) as u8, (state >> 8) as u8])
+        });
+        let max_bytes = 8000;
+
+        // Act
+        let quality = device
+            .set_button_image_within_budget(0, &image, max_bytes)
+            .unwrap();
+        let too_small = device.set_button_image_within_budget(0, &image, 100);
+
+        // Test
+        assert!(quality < 100);
+        let written = written.lock().unwrap();
+        let payload: usize = written
+            .iter()
+            .map(|package| package[4] as usize | (package[5] as usize) << 8)
+            .sum();
+        assert!(payload <= max_bytes);
+        assert!(matches!(
+            too_small,
+            Err(Error::ImageTooLarge { max_bytes: 100, .. })
+        ));
+        assert!(matches!(
+            mock_device(StreamDeckType::Mini).set_button_image_within_budget(
+                0,
+                &RgbImage::new(80, 80),
+                max_bytes
+            ),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_set_button_images_map() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let image = RgbImage::new(72, 72);
+        let mut images = HashMap::new();
+        images.insert(4, image.clone());
+        images.insert(1, image.clone());
+
+        // Act
+        device.set_button_images_map(&images).unwrap();
+
+        // Test
+        let mut expected = crate::image::image_packages(StreamDeckType::Orig, &image, 1).unwrap();
+        expected.extend(crate::image::image_packages(StreamDeckType::Orig, &image, 4).unwrap());
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_button_image_stats() {
+        // Setup
+        let device = mock_device(StreamDeckType::Orig);
+        let image = RgbImage::new(72, 72);
+
+        // Act
+        let stats = device.set_button_image_stats(0, &image).unwrap();
+
+        // Test
+        assert_eq!(stats.packets, 2);
+    }
+
+    #[test]
+    fn test_set_assume_native_orientation() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        let mut image = RgbImage::new(96, 96);
+        image.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+
+        // Act
+        device.set_assume_native_orientation(true);
+        device.set_button_image(0, &image).unwrap();
+
+        // Test
+        let expected = crate::image::image_packages(
+            StreamDeckType::Xl,
+            &image::imageops::rotate180(&image),
+            0,
+        )
+        .unwrap();
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_dimension_policy() {
+        // Setup
+        let mut device = mock_device(StreamDeckType::Xl);
+        let image = RgbImage::new(95, 96);
+
+        // Act
+        let strict = device.set_button_image(0, &image);
+        device.set_dimension_policy(DimensionPolicy::AutoFit);
+        let auto_fit = device.set_button_image(0, &image);
+
+        // Test
+        assert!(matches!(strict, Err(Error::DimensionMismatch(96, 96))));
+        assert!(auto_fit.is_ok());
+    }
+
+    #[test]
+    fn test_prepared_animation() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        let frames = [
+            StreamDeckType::Xl.test_pattern(crate::TestPattern::Checker),
+            StreamDeckType::Xl.test_pattern(crate::TestPattern::Gradient),
+            StreamDeckType::Xl.test_pattern(crate::TestPattern::SolidColor(image::Rgb([1, 2, 3]))),
+        ];
+
+        // Act
+        let animation = device.prepare_animation(&frames).unwrap();
+        let written_after_prepare = written.lock().unwrap().len();
+        device
+            .play_prepared_animation(5, &animation, Duration::ZERO)
+            .unwrap();
+
+        // Test
+        assert_eq!(animation.num_frames(), 3);
+        assert_eq!(written_after_prepare, 0);
+        let expected: Vec<Vec<u8>> = frames
+            .iter()
+            .flat_map(|frame| crate::image::image_packages(StreamDeckType::Xl, frame, 5).unwrap())
+            .collect();
+        assert_eq!(*written.lock().unwrap(), expected);
+        assert!(matches!(
+            mock_device(StreamDeckType::Mini).play_prepared_animation(
+                0,
+                &animation,
+                Duration::ZERO
+            ),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_set_key_sensitivity_unsupported() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let mut hid_device = MockHidDeviceTrait::new();
+            hid_device.expect_send_feature_report().never();
+            let device = StreamDeckDevice::<MockMockHidApi>::new(device_type, hid_device);
+
+            // Act
+            let result = device.set_key_sensitivity(3);
+
+            // Test
+            assert!(matches!(result, Err(Error::Unsupported)));
+        }
+    }
+
+    #[test]
+    fn test_fade_brightness() {
+        // Setup
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let sent_clone = sent.clone();
+        hid_device
+            .expect_send_feature_report()
+            .returning(move |data: &[u8]| {
+                sent_clone.lock().unwrap().push(data[2]);
+                Ok(())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.set_brightness(10).unwrap();
+        sent.lock().unwrap().clear();
+
+        // Act
+        device
+            .fade_brightness(80, Duration::from_millis(4), 4)
+            .unwrap();
+
+        // Test
+        assert_eq!(*sent.lock().unwrap(), vec![27, 45, 62, 80]);
+    }
+
+    /// Set all button images on a mocked XL and return the written packets.
+    fn written_set_all_button_images(
+        setup: impl FnOnce(&mut StreamDeckDevice<MockMockHidApi>),
+        images: &[RgbImage],
+    ) -> Vec<Vec<u8>> {
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        setup(&mut device);
+        device.set_all_button_images(images).unwrap();
+        let written = written.lock().unwrap().clone();
+        written
+    }
+
+    #[test]
+    fn test_set_all_button_images() {
+        // Setup
+        let images: Vec<RgbImage> = (0..3)
+            .map(|index| RgbImage::from_pixel(96, 96, image::Rgb([index * 80, 0, 0])))
+            .collect();
+
+        // Act
+        let written = written_set_all_button_images(|_device| {}, &images);
+
+        // Test
+        let mut expected = Vec::new();
+        for (button_id, image) in images.iter().enumerate() {
+            expected.extend(
+                crate::image::image_packages(StreamDeckType::Xl, image, button_id as u8).unwrap(),
+            );
+        }
+        assert_eq!(written, expected);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_set_all_button_images_parallel() {
+        // Setup
+        let images: Vec<RgbImage> = (0..32)
+            .map(|index| StreamDeckType::Xl.gauge_image(index as f32 / 31.0))
+            .collect();
+
+        // Act
+        let sequential = written_set_all_button_images(|_device| {}, &images);
+        let parallel =
+            written_set_all_button_images(|device| device.set_parallel_encoding(true), &images);
+
+        // Test
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn test_present() {
+        // Setup
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let calls_clone = calls.clone();
+        hid_device
+            .expect_send_feature_report()
+            .returning(move |data: &[u8]| {
+                calls_clone.lock().unwrap().push(data.to_vec());
+                Ok(())
+            });
+        let calls_clone = calls.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            calls_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let images = vec![RgbImage::new(80, 80); 2];
+
+        // Act
+        device.present(&images, 65).unwrap();
+
+        // Test
+        let mut expected = vec![StreamDeckType::Mini.brightness_packet(0)];
+        expected.extend(crate::image::image_packages(StreamDeckType::Mini, &images[0], 0).unwrap());
+        expected.extend(crate::image::image_packages(StreamDeckType::Mini, &images[1], 1).unwrap());
+        expected.push(StreamDeckType::Mini.brightness_packet(65));
+        assert_eq!(*calls.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_power_off_and_on() {
+        // Setup
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let sent_clone = sent.clone();
+        hid_device
+            .expect_send_feature_report()
+            .returning(move |data: &[u8]| {
+                sent_clone.lock().unwrap().push(data[2]);
+                Ok(())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.set_brightness(35).unwrap();
+
+        // Act
+        let on_before = device.is_on();
+        device.power_off().unwrap();
+        let on_after_off = device.is_on();
+        device.power_on().unwrap();
+
+        // Test
+        assert!(on_before);
+        assert!(!on_after_off);
+        assert!(device.is_on());
+        assert_eq!(*sent.lock().unwrap(), vec![35, 0, 35]);
+    }
+
+    #[test]
+    fn test_power_on_default_brightness() {
+        // Setup
+        let device = mock_device(StreamDeckType::Mini);
+        device.set_brightness(0).unwrap();
+
+        // Act
+        device.power_on().unwrap();
+
+        // Test
+        assert!(device.is_on());
+        assert_eq!(
+            *device.brightness.lock().unwrap(),
+            Some(StreamDeckType::Mini.default_brightness())
+        );
+    }
+
+    #[test]
+    fn test_set_button_image_raw() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xD9];
+        let mut expected = StreamDeckType::Xl.image_package_header(jpeg.len(), 3, 0);
+        expected.extend(&jpeg);
+        expected.resize(StreamDeckType::Xl.image_package_size(), 0);
+        hid_device
+            .expect_write()
+            .with(eq(expected))
+            .times(1)
+            .returning(|data: &[u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+
+        // Act
+        let valid = device.set_button_image_raw(3, &jpeg);
+        let invalid = device.set_button_image_raw(3, b"BM\x36\x3c");
+
+        // Test
+        assert!(valid.is_ok());
+        assert!(matches!(invalid, Err(Error::InvalidEncodedImage)));
+    }
+
+    #[test]
+    fn test_transition_to_left_to_right() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let images: Vec<RgbImage> = (0..6)
+            .map(|index| RgbImage::from_pixel(80, 80, image::Rgb([index * 40, 0, 0])))
+            .collect();
+
+        // Act
+        device
+            .transition_to(&images, TransitionStyle::LeftToRight, Duration::ZERO)
+            .unwrap();
+
+        // Test
+        // The Mini has 2 rows with 3 columns
+        let expected: Vec<Vec<u8>> = [0, 3, 1, 4, 2, 5]
+            .into_iter()
+            .flat_map(|button_id: u8| {
+                device
+                    .image_packages(button_id, &images[button_id as usize], &mut Vec::new())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut values: Vec<usize> = (0..20).collect();
+        shuffle(&mut values);
+        values.sort();
+        assert_eq!(values, (0..20).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_plus_input_kind_classify() {
+        assert_eq!(
+            PlusInputKind::classify(&[0x01, 0x00, 0x08, 0x00, 0x01]),
+            Some(PlusInputKind::Buttons)
+        );
+        assert_eq!(
+            PlusInputKind::classify(&[0x01, 0x02, 0x0e, 0x00, 0x01]),
+            Some(PlusInputKind::Touch)
+        );
+        assert_eq!(
+            PlusInputKind::classify(&[0x01, 0x03, 0x05, 0x00, 0x01]),
+            Some(PlusInputKind::Encoders)
+        );
+        assert_eq!(PlusInputKind::classify(&[0x01, 0x07]), None);
+        assert_eq!(PlusInputKind::classify(&[0x01]), None);
+    }
+
+    #[test]
+    fn test_suppress_input() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let reads = std::sync::atomic::AtomicUsize::new(0);
+        hid_device
+            .expect_read_timeout()
+            .returning(move |data: &mut [u8], timeout: i32| {
+                assert!(timeout >= 1, "busy waiting with timeout {}", timeout);
+                // Button 2 is pressed during the suppression, then nothing happens
+                if reads.fetch_add(1, std::sync::atomic::Ordering::Relaxed) == 0 {
+                    data.fill(0);
+                    data[StreamDeckType::Mini.button_read_offset() + 2] = 1;
+                    Ok(data.len())
+                } else {
+                    std::thread::sleep(Duration::from_millis(1));
+                    Ok(0)
+                }
+            });
+        // Button 2 is released after the suppression, then pressed again
+        let mut sequence = Sequence::new();
+        for pressed in [false, true] {
+            hid_device
+                .expect_read()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |data: &mut [u8]| {
+                    data.fill(0);
+                    data[StreamDeckType::Mini.button_read_offset() + 2] = pressed as u8;
+                    Ok(data.len())
+                });
+        }
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        device.suppress_input(Duration::from_millis(10)).unwrap();
+        let states = device.last_button_states();
+        let release_events = device.read_button_events().unwrap();
+        let press_events = device.read_button_events().unwrap();
+
+        // Test
+        assert_eq!(states[2], ButtonState::Down);
+        assert!(release_events.is_empty());
+        assert_eq!(press_events.len(), 1);
+        assert_eq!(press_events[0].button_id, 2);
+        assert_eq!(press_events[0].state, ButtonState::Down);
+    }
+
+    #[test]
+    fn test_encoder_positions() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        for report in [
+            [0x01, 0x03, 0x05, 0x00, 0x01, 0x03, 0xFF, 0x00, 0x00],
+            [0x01, 0x03, 0x05, 0x00, 0x01, 0x02, 0xFF, 0x00, 0x01],
+            // Pressing the encoders does not change the position
+            [0x01, 0x03, 0x05, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00],
+        ] {
+            hid_device
+                .expect_read()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |data: &mut [u8]| {
+                    data.fill(0);
+                    data[..report.len()].copy_from_slice(&report);
+                    Ok(report.len())
+                });
+        }
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Plus, hid_device);
+
+        // Act
+        let result = device.on_button_events(|_event| {});
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        assert_eq!(device.encoder_position(0), 5);
+        assert_eq!(device.encoder_position(1), -2);
+        assert_eq!(device.encoder_position(2), 0);
+        assert_eq!(device.encoder_position(3), 1);
+        assert_eq!(device.encoder_position(4), 0);
+        device.reset_encoder_position(0);
+        assert_eq!(device.encoder_position(0), 0);
+        assert_eq!(device.encoder_position(1), -2);
+    }
+
+    #[test]
+    fn test_run_button_events_stops() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset()] = 1;
+                Ok(data.len())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device.run_button_events(
+            |event| events.borrow_mut().push(event),
+            || events.borrow().is_empty(),
+        );
+
+        // Test
+        assert!(result.is_ok());
+        let events = events.into_inner();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].button_id, 0);
+        assert_eq!(events[0].state, ButtonState::Down);
+    }
+
+    #[test]
+    fn test_on_button_event_frames() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset()] = 1;
+                data[StreamDeckType::Mini.button_read_offset() + 3] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let frames = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device.on_button_event_frames(|events| frames.borrow_mut().push(events.len()));
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        assert_eq!(frames.into_inner(), vec![2]);
+    }
+
+    /// A mocked device returning the reports, then an error to end the event loop.
+    fn device_reading_reports(
+        device_type: StreamDeckType,
+        reports: Vec<Vec<u8>>,
+    ) -> StreamDeckDevice<MockMockHidApi> {
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        for report in reports {
+            hid_device
+                .expect_read()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |data: &mut [u8]| {
+                    data[..report.len()].copy_from_slice(&report);
+                    Ok(report.len())
+                });
+        }
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        StreamDeckDevice::<MockMockHidApi>::new(device_type, hid_device)
+    }
+
+    #[test]
+    fn test_press_flash() {
+        // Setup
+        let offset = StreamDeckType::Xl.button_read_offset();
+        let mut pressed = vec![0; offset + 32];
+        pressed[offset + 2] = 1;
+        let mut device =
+            device_reading_reports(StreamDeckType::Xl, vec![pressed, vec![0; offset + 32]]);
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let written_clone = written.clone();
+        device
+            .hid_device
+            .expect_write()
+            .returning(move |data: &[u8]| {
+                written_clone.lock().unwrap().push(data.to_vec());
+                Ok(data.len())
+            });
+        let image = RgbImage::from_pixel(96, 96, Rgb([100, 60, 20]));
+        device.set_button_image(2, &image).unwrap();
+        device.set_press_flash(true, 1.5);
+        written.lock().unwrap().clear();
+
+        // Act
+        let result = device.on_button_events(|_event| {});
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        let brightened = RgbImage::from_pixel(96, 96, Rgb([150, 90, 30]));
+        let mut expected =
+            crate::image::image_packages(StreamDeckType::Xl, &brightened, 2).unwrap();
+        expected.extend(crate::image::image_packages(StreamDeckType::Xl, &image, 2).unwrap());
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_press_flash_released_in_heartbeat() {
+        // Setup
+        let offset = StreamDeckType::Xl.button_read_offset();
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(move |data: &mut [u8]| {
+                data.fill(0);
+                data[offset + 2] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .returning(|data: &mut [u8], _timeout: i32| {
+                data.fill(0);
+                Ok(data.len())
+            });
+        hid_device
+            .expect_get_feature_report()
+            .returning(|data: &mut [u8]| Ok(data.len()));
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        let image = RgbImage::from_pixel(96, 96, Rgb([100, 60, 20]));
+        device.set_button_image(2, &image).unwrap();
+        device.set_press_flash(true, 1.5);
+        written.lock().unwrap().clear();
+
+        // Act
+        device.read_button_events().unwrap();
+        let pressed_writes = written.lock().unwrap().len();
+        device.heartbeat().unwrap();
+
+        // Test
+        let brightened = RgbImage::from_pixel(96, 96, Rgb([150, 90, 30]));
+        let mut expected =
+            crate::image::image_packages(StreamDeckType::Xl, &brightened, 2).unwrap();
+        assert_eq!(pressed_writes, expected.len());
+        expected.extend(crate::image::image_packages(StreamDeckType::Xl, &image, 2).unwrap());
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_press_flash_write_failed() {
+        // Setup
+        let offset = StreamDeckType::Xl.button_read_offset();
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(move |data: &mut [u8]| {
+                data.fill(0);
+                data[offset + 2] = 1;
+                Ok(data.len())
+            });
+        let fail = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let fail_clone = fail.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            if fail_clone.load(std::sync::atomic::Ordering::Relaxed) {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            } else {
+                Ok(data.len())
+            }
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.set_button_image(2, &RgbImage::new(96, 96)).unwrap();
+        device.set_press_flash(true, 1.5);
+        fail.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        // Act
+        let events = device.read_button_events().unwrap();
+
+        // Test
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].button_id, 2);
+        assert_eq!(events[0].state, ButtonState::Down);
+    }
+
+    #[test]
+    fn test_press_flash_not_while_suppressed() {
+        // Setup
+        let offset = StreamDeckType::Mini.button_read_offset();
+        let mut hid_device = MockHidDeviceTrait::new();
+        let reads = std::sync::atomic::AtomicUsize::new(0);
+        hid_device
+            .expect_read_timeout()
+            .returning(move |data: &mut [u8], _timeout: i32| {
+                if reads.fetch_add(1, std::sync::atomic::Ordering::Relaxed) == 0 {
+                    data.fill(0);
+                    data[offset + 2] = 1;
+                    Ok(data.len())
+                } else {
+                    std::thread::sleep(Duration::from_millis(1));
+                    Ok(0)
+                }
+            });
+        let writes = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let writes_clone = writes.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            writes_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        device.set_button_image(2, &RgbImage::new(80, 80)).unwrap();
+        device.set_press_flash(true, 1.5);
+        let writes_before = writes.load(std::sync::atomic::Ordering::Relaxed);
+
+        // Act
+        device.suppress_input(Duration::from_millis(5)).unwrap();
+
+        // Test
+        assert_eq!(device.last_button_states()[2], ButtonState::Down);
+        assert_eq!(
+            writes.load(std::sync::atomic::Ordering::Relaxed),
+            writes_before
+        );
+    }
+
+    #[test]
+    fn test_on_input_events_buttons_only() {
+        // Setup
+        let offset = StreamDeckType::Mini.button_read_offset();
+        let mut pressed = vec![0; offset + 6];
+        pressed[offset + 4] = 1;
+        let device =
+            device_reading_reports(StreamDeckType::Mini, vec![pressed, vec![0; offset + 6]]);
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device.on_input_events(|event| events.borrow_mut().push(event));
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        let events = events.into_inner();
+        assert_eq!(events.len(), 2);
+        for (event, state) in events.iter().zip([ButtonState::Down, ButtonState::Up]) {
+            match event {
+                InputEvent::Button(event) => {
+                    assert_eq!(event.button_id, 4);
+                    assert_eq!(event.state, state);
+                }
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_on_input_events_plus() {
+        // Setup
+        let reports = vec![
+            // Button 1 down
+            vec![0x01, 0x00, 0x08, 0x00, 0, 1, 0, 0, 0, 0, 0, 0],
+            // Encoder 0 turned right by 2, encoder 3 left by 1
+            vec![0x01, 0x03, 0x05, 0x00, 0x01, 2, 0, 0, 0xFF],
+            // Encoder 2 pressed
+            vec![0x01, 0x03, 0x05, 0x00, 0x00, 0, 0, 1, 0],
+            // Short tap at 300/50
+            vec![0x01, 0x02, 0x0e, 0x00, 0x01, 0x00, 0x2c, 0x01, 0x32, 0x00],
+            // Swipe from 100/20 to 700/30
+            vec![
+                0x01, 0x02, 0x0e, 0x00, 0x03, 0x00, 0x64, 0x00, 0x14, 0x00, 0xbc, 0x02, 0x1e, 0x00,
+            ],
+        ];
+        let device = device_reading_reports(StreamDeckType::Plus, reports);
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device.on_input_events(|event| events.borrow_mut().push(event));
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        let events = events.into_inner();
+        assert_eq!(events.len(), 6, "{:?}", events);
+        assert!(matches!(
+            events[0],
+            InputEvent::Button(ButtonEvent {
+                button_id: 1,
+                state: ButtonState::Down
+            })
+        ));
+        assert!(matches!(
+            events[1],
+            InputEvent::Encoder(EncoderEvent::Turn {
+                encoder: 0,
+                delta: 2
+            })
+        ));
+        assert!(matches!(
+            events[2],
+            InputEvent::Encoder(EncoderEvent::Turn {
+                encoder: 3,
+                delta: -1
+            })
+        ));
+        assert!(matches!(
+            events[3],
+            InputEvent::Encoder(EncoderEvent::Press {
+                encoder: 2,
+                state: ButtonState::Down
+            })
+        ));
+        assert!(matches!(
+            events[4],
+            InputEvent::Touch(TouchEvent::Short { x: 300, y: 50 })
+        ));
+        assert!(matches!(
+            events[5],
+            InputEvent::Touch(TouchEvent::Drag {
+                x: 100,
+                y: 20,
+                x_out: 700,
+                y_out: 30
+            })
+        ));
+        assert_eq!(device.encoder_position(0), 2);
+        assert_eq!(device.encoder_position(3), -1);
+    }
+
+    #[test]
+    fn test_benchmark_upload() {
+        // Setup
+        let packets_per_image = crate::image::image_packages(
+            StreamDeckType::Xl,
+            &StreamDeckType::Xl.test_pattern(TestPattern::Gradient),
+            0,
+        )
+        .unwrap()
+        .len();
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_write()
+            .times(5 * packets_per_image)
+            .returning(|data: &[u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+
+        // Act
+        let benchmark = device.benchmark_upload(5).unwrap();
+
+        // Test
+        assert_eq!(benchmark.iterations, 5);
+        assert_eq!(benchmark.packets, 5 * packets_per_image);
+        assert_eq!(benchmark.bytes, benchmark.packets * 1024);
+        assert!(benchmark.bytes_per_second >= 0.0);
+        assert!(benchmark.packets_per_second <= benchmark.bytes_per_second);
+    }
+
+    #[test]
+    fn test_reset_button() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.set_default_background(Rgb([0, 0, 255]));
+
+        // Act
+        device.reset_button(3).unwrap();
+
+        // Test
+        let black = RgbImage::new(96, 96);
+        assert_eq!(
+            *written.lock().unwrap(),
+            crate::image::image_packages(StreamDeckType::Xl, &black, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_clear_button_image_default_background() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.set_default_background(Rgb([0, 0, 255]));
+
+        // Act
+        device.clear_button_image(3).unwrap();
+
+        // Test
+        let packages = written.lock().unwrap().clone();
+        let jpeg = crate::unpack_image_packets(StreamDeckType::Xl, &packages).unwrap();
+        let uploaded = image::load_from_memory(&jpeg).unwrap().to_rgb8();
+        let center = uploaded.get_pixel(uploaded.width() / 2, uploaded.height() / 2);
+        assert!(
+            center[0] < 10 && center[1] < 10 && center[2] > 245,
+            "{:?}",
+            center
+        );
+        assert_eq!(packages[0][2], 3);
+    }
+
+    #[test]
+    fn test_error_context() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_send_feature_report()
+            .returning(|_data: &[u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        device.serial_number = Some(String::from("CL12345"));
+        device.set_error_context(true);
+
+        // Act
+        let error = device.set_brightness(50).unwrap_err();
+
+        // Test
+        let message = error.to_string();
+        assert!(message.contains(StreamDeckType::Xl.name()), "{}", message);
+        assert!(message.contains("CL12345"), "{}", message);
+        // The cause is only reported as source, not repeated in the message
+        assert!(!message.contains("device disconnected"), "{}", message);
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), "hid error");
+        let cause = source.source().unwrap().to_string();
+        assert!(cause.contains("device disconnected"), "{}", cause);
+        assert!(matches!(error.without_device(), Error::HidError(_)));
+    }
+
+    #[test]
+    fn test_input_available() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8], _timeout: i32| Ok(0));
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &mut [u8], _timeout: i32| {
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset() + 3] = 1;
+                Ok(data.len())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let not_ready = device.input_available(0).unwrap();
+        let ready = device.input_available(0).unwrap();
+
+        // Test
+        assert!(!not_ready);
+        assert!(ready);
+        assert_eq!(device.last_button_states()[3], ButtonState::Down);
+    }
+
+    #[test]
+    fn test_last_button_states_after_error() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset() + 4] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        device.set_panel_rotation(Rotation::Upside180);
+
+        // Act
+        let result = device.on_button_events(|_event| {});
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        let mut expected = vec![ButtonState::Up; 6];
+        expected[1] = ButtonState::Down;
+        assert_eq!(device.last_button_states(), expected);
+    }
+
+    #[test]
+    fn test_on_button_events_repeat() {
+        // Setup
+        let offset = StreamDeckType::Mini.button_read_offset();
+        let interval = Duration::from_millis(100);
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        // Button 1 is pressed, button 2 is pressed 60 ms later
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move |data: &mut [u8]| {
+                data.fill(0);
+                data[offset + 1] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move |data: &mut [u8], _timeout: i32| {
+                std::thread::sleep(Duration::from_millis(60));
+                data.fill(0);
+                data[offset + 1] = 1;
+                data[offset + 2] = 1;
+                Ok(data.len())
+            });
+        // Nothing happens until the repeat of button 1 is due
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8], timeout: i32| {
+                assert!(
+                    timeout <= 40,
+                    "waiting {} ms for the repeat of button 1",
+                    timeout
+                );
+                std::thread::sleep(Duration::from_millis(timeout as u64));
+                Ok(0)
+            });
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8], _timeout: i32| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device
+            .on_button_events_repeat(interval, |event| events.borrow_mut().push(event.button_id));
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        // Button 2 was pressed late, it is not repeated together with button 1
+        assert_eq!(events.into_inner(), vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn test_on_button_grid_events() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Xl.button_read_offset() + 5] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        let result = device.on_button_grid_events(|event| events.borrow_mut().push(event));
+
+        // Test
+        assert!(matches!(result, Err(Error::HidError(_))));
+        let events = events.into_inner();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].row, 0);
+        assert_eq!(events[0].col, 5);
+        assert_eq!(events[0].state, ButtonState::Down);
+    }
+
+    #[test]
+    fn test_read_events_into() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(|data: &mut [u8]| {
+                let offset = StreamDeckType::Orig.button_read_offset();
+                data[..offset + 15].fill(0);
+                data[offset + 1] = 1;
+                data[offset + 7] = 1;
+                Ok(offset + 15)
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let mut buf = [0u8; 32];
+        let mut state = [ButtonState::Up; 15];
+        let mut events = [
+            ButtonEvent {
+                button_id: 0,
+                state: ButtonState::Up,
+            },
+            ButtonEvent {
+                button_id: 0,
+                state: ButtonState::Up,
+            },
+        ];
+
+        // Act
+        let count = device
+            .read_events_into(&mut events, &mut state, &mut buf)
+            .unwrap();
+
+        // Test
+        assert_eq!(count, 2);
+        assert_eq!(events[0].button_id, 1);
+        assert_eq!(events[0].state, ButtonState::Down);
+        assert_eq!(events[1].button_id, 7);
+        assert_eq!(events[1].state, ButtonState::Down);
+        assert_eq!(state[1], ButtonState::Down);
+        assert_eq!(state[7], ButtonState::Down);
+    }
+
+    #[test]
+    fn test_is_alive() {
+        // Setup
+        let mut alive_device = MockHidDeviceTrait::new();
+        alive_device
+            .expect_get_feature_report()
+            .returning(|data: &mut [u8]| Ok(data.len()));
+        let mut dead_device = MockHidDeviceTrait::new();
+        dead_device
+            .expect_get_feature_report()
+            .returning(|_data: &mut [u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+
+        // Act
+        let alive = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, alive_device);
+        let dead = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, dead_device);
 
-        let mut button_state = vec![ButtonState::Up; self.device_type.total_num_buttons() as usize];
+        // Test
+        assert!(alive.is_alive());
+        assert!(!dead.is_alive());
+    }
 
-        loop {
-            match self.hid_device.read(&mut inbuffer) {
-                Result::Ok(_) => {}
-                Result::Err(e) => return Err(Error::HidError(e)),
-            };
-            debug!("Streamdeck read: {:?}", inbuffer);
-            for button_id in 0..self.device_type.total_num_buttons() {
-                if inbuffer[button_id + self.device_type.button_read_offset()] == 0 {
-                    if button_state[button_id] == ButtonState::Down {
-                        cb(ButtonEvent {
-                            button_id: button_id as u32,
-                            state: ButtonState::Up,
-                        });
-                        button_state[button_id] = ButtonState::Up;
-                    }
-                } else if button_state[button_id] == ButtonState::Up {
-                    cb(ButtonEvent {
-                        button_id: button_id as u32,
-                        state: ButtonState::Down,
-                    });
-                    button_state[button_id] = ButtonState::Down;
-                }
-            }
-        }
+    #[test]
+    fn test_set_panel_rotation_upside180() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(|data: &mut [u8]| {
+                // Press the physical bottom right button
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset() + 5] = 1;
+                Ok(data.len())
+            });
+        let mut device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        device.set_panel_rotation(Rotation::Upside180);
+        let mut image = RgbImage::new(80, 80);
+        image.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+        let events = std::cell::RefCell::new(Vec::new());
+
+        // Act
+        device.set_button_image(0, &image).unwrap();
+        device
+            .run_button_events(
+                |event| events.borrow_mut().push(event),
+                || events.borrow().is_empty(),
+            )
+            .unwrap();
+
+        // Test
+        let expected =
+            crate::image::image_packages(StreamDeckType::Mini, &imageops::rotate180(&image), 5)
+                .unwrap();
+        assert_eq!(*written.lock().unwrap(), expected);
+        assert_eq!(events.borrow()[0].button_id, 0);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    #[allow(unused_imports)]
-    use super::*;
-    #[allow(unused_imports)]
-    use crate::Error::HidError;
-    #[allow(unused_imports)]
-    use mockall::predicate::*;
-    #[allow(unused_imports)]
-    use mockall::*;
+    #[test]
+    fn test_set_brightness_all() {
+        // Setup
+        let devices: Vec<StreamDeckDevice<MockMockHidApi>> =
+            [StreamDeckType::Xl, StreamDeckType::Mini]
+                .into_iter()
+                .map(|device_type| {
+                    let mut hid_device = MockHidDeviceTrait::new();
+                    hid_device
+                        .expect_send_feature_report()
+                        .with(eq(device_type.brightness_packet(30)))
+                        .times(1)
+                        .returning(|_data: &[u8]| Ok(()));
+                    StreamDeckDevice::new(device_type, hid_device)
+                })
+                .collect();
+
+        // Act
+        let results = set_brightness_all(&devices, 30);
+
+        // Test
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
 
     #[test]
-    fn test_list_devices_empty() {
+    fn test_reset_plus_clears_touchscreen() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        hid_device
+            .expect_send_feature_report()
+            .with(eq(StreamDeckType::Plus.reset_packet()))
+            .times(1)
+            .returning(|_data: &[u8]| Ok(()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Plus, hid_device);
+
+        // Act
+        device.reset().unwrap();
+
+        // Test
+        let written = written.lock().unwrap();
+        assert_eq!(written[0], StreamDeckType::Plus.reset_key_stream_packet());
+        let clear = crate::image::touchscreen_image_packages(
+            StreamDeckType::Plus,
+            &RgbImage::new(800, 100),
+        )
+        .unwrap();
+        assert_eq!(written[1..], clear[..]);
+    }
+
+    #[test]
+    fn test_clear_images_only() {
+        // Setup (the mock fails on any other call, like the reset feature report)
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_write()
+            .with(eq(StreamDeckType::Mini.reset_key_stream_packet()))
+            .times(1)
+            .returning(|data: &[u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let result = device.clear_images_only();
+
+        // Test
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_self_test() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_write()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &[u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        hid_device
+            .expect_write()
+            .in_sequence(&mut sequence)
+            .returning(|data: &[u8]| Ok(data.len()));
+        hid_device
+            .expect_send_feature_report()
+            .returning(|_data: &[u8]| Ok(()));
+        hid_device
+            .expect_read_timeout()
+            .times(3)
+            .returning(|_data: &mut [u8], _timeout: i32| Ok(0));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let report = device.self_test();
+
+        // Test
+        let names: Vec<&str> = report.steps.iter().map(|step| step.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "reset",
+                "set brightness",
+                "set image of button 0",
+                "set image of button 1",
+                "set image of button 2",
+                "set image of button 3",
+                "set image of button 4",
+                "set image of button 5",
+                "read report 0",
+                "read report 1",
+                "read report 2",
+            ]
+        );
+        assert!(!report.passed());
+        let failures: Vec<&str> = report.failures().map(|step| step.name.as_str()).collect();
+        assert_eq!(failures, vec!["reset"]);
+    }
+
+    #[test]
+    fn test_reset_all() {
+        // Setup
+        let devices: Vec<StreamDeckDevice<MockMockHidApi>> =
+            (0..2).map(|_| mock_device(StreamDeckType::Orig)).collect();
+
+        // Act
+        let results = reset_all(&devices);
+
+        // Test
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn test_set_button_image_if_changed() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_write()
+            .times(2)
+            .returning(|data: &[u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let image = RgbImage::new(72, 72);
+
+        // Act
+        let first = device.set_button_image_if_changed(3, &image).unwrap();
+        let second = device.set_button_image_if_changed(3, &image).unwrap();
+
+        // Test (the mock fails if more than the two packets of the first upload are written)
+        assert!(first);
+        assert!(!second);
+    }
+
+    #[test]
+    fn test_render_panel_contact_sheet() {
+        // Setup
+        let device = mock_device(StreamDeckType::Mini);
+        let red = image::Rgb([255, 0, 0]);
+        let blue = image::Rgb([0, 0, 255]);
+        device
+            .set_button_image(0, &RgbImage::from_pixel(80, 80, red))
+            .unwrap();
+        device
+            .set_button_image(4, &RgbImage::from_pixel(80, 80, blue))
+            .unwrap();
+
+        // Act
+        let sheet = device.render_panel_contact_sheet();
+
+        // Test
+        let gap = CONTACT_SHEET_GAP;
+        assert_eq!(
+            sheet.dimensions(),
+            (3 * (80 + gap) + gap, 2 * (80 + gap) + gap)
+        );
+        assert_eq!(*sheet.get_pixel(gap + 40, gap + 40), red);
+        // Button 4 is in the second row, second column
+        assert_eq!(*sheet.get_pixel(2 * gap + 80 + 40, 2 * gap + 80 + 40), blue);
+        assert_eq!(
+            *sheet.get_pixel(2 * gap + 80 + 40, gap + 40),
+            image::Rgb([0, 0, 0])
+        );
+        assert_eq!(*sheet.get_pixel(0, 0), CONTACT_SHEET_GAP_COLOR);
+    }
+
+    #[test]
+    fn test_button_needs_update() {
+        // Setup
+        let device = mock_device(StreamDeckType::Orig);
+        let image = RgbImage::new(72, 72);
+        let other_image = RgbImage::from_pixel(72, 72, image::Rgb([255, 0, 0]));
+
+        // Act
+        let before_upload = device.button_needs_update(2, &image);
+        device.set_button_image(2, &image).unwrap();
+
+        // Test
+        assert!(before_upload);
+        assert!(!device.button_needs_update(2, &image));
+        assert!(device.button_needs_update(2, &other_image));
+        assert!(device.button_needs_update(3, &image));
+    }
+
+    #[test]
+    fn test_cache_hash_mode_fast() {
+        // Setup
+        let mut device = mock_device(StreamDeckType::Orig);
+        let image = RgbImage::new(72, 72);
+        let mut sampled_row_changed = image.clone();
+        sampled_row_changed.put_pixel(10, 8, image::Rgb([255, 0, 0]));
+        let mut other_row_changed = image.clone();
+        other_row_changed.put_pixel(10, 9, image::Rgb([255, 0, 0]));
+
+        // Act
+        device.set_cache_hash_mode(CacheHashMode::Fast);
+        device.set_button_image(0, &image).unwrap();
+
+        // Test
+        assert!(!device.button_needs_update(0, &image));
+        assert!(device.button_needs_update(0, &sampled_row_changed));
+        // The documented tradeoff: changes between the sampled rows are missed
+        assert!(!device.button_needs_update(0, &other_row_changed));
+    }
+
+    #[test]
+    fn test_cache_hash_mode_fast_after_upload() {
+        // Setup
+        let mut device = mock_device(StreamDeckType::Orig);
+        let image = RgbImage::from_pixel(72, 72, image::Rgb([10, 20, 30]));
+        let other_image = RgbImage::new(72, 72);
+        device.set_button_image(0, &image).unwrap();
+
+        // Act
+        device.set_cache_hash_mode(CacheHashMode::Fast);
+        let needs_update_after_switch = device.button_needs_update(0, &image);
+        device.set_button_image(1, &other_image).unwrap();
+        device.clear_framebuffer();
+
+        // Test
+        assert!(!needs_update_after_switch);
+        assert!(device.button_needs_update(0, &image));
+        assert!(device.button_needs_update(1, &other_image));
+    }
+
+    #[test]
+    fn test_set_neo_info_image() {
+        // Setup
+        let neo = mock_device(StreamDeckType::Neo);
+        let mini = mock_device(StreamDeckType::Mini);
+        let image = RgbImage::new(248, 58);
+
+        // Act
+        let neo_result = neo.set_neo_info_image(&image);
+        let mini_result = mini.set_neo_info_image(&image);
+
+        // Test
+        assert!(neo_result.is_ok());
+        assert!(matches!(mini_result, Err(Error::Unsupported)));
+    }
+
+    #[test]
+    fn test_set_button_image_write_failed() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_write()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &[u8]| Ok(data.len()));
+        hid_device
+            .expect_write()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &[u8]| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let result = device.set_button_image(4, &RgbImage::new(80, 80));
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::WriteFailed {
+                button_id: Some(4),
+                packet_index: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_open_ready() {
         // Setup
         let mut api_mock = MockMockHidApi::new();
-        api_mock.expect_device_list().times(1).returning(Vec::new);
+        api_mock.expect_device_list().returning(|| {
+            let mut info_mock = MockDeviceInfoTrait::new();
+            info_mock
+                .expect_vendor_id()
+                .returning(|| StreamDeckType::Xl.get_vendor_id());
+            info_mock
+                .expect_product_id()
+                .returning(|| StreamDeckType::Xl.get_product_id());
+            info_mock
+                .expect_path()
+                .returning(|| String::from("/dev/hidraw0"));
+            info_mock.expect_serial_number().returning(|| None);
+            Vec::from([info_mock])
+        });
+        api_mock
+            .expect_open_path()
+            .times(1)
+            .returning(|_path: &str| {
+                let mut hid_device = MockHidDeviceTrait::new();
+                let mut sequence = Sequence::new();
+                hid_device
+                    .expect_write()
+                    .with(eq(StreamDeckType::Xl.reset_key_stream_packet()))
+                    .times(1)
+                    .in_sequence(&mut sequence)
+                    .returning(|data: &[u8]| Ok(data.len()));
+                hid_device
+                    .expect_send_feature_report()
+                    .with(eq(StreamDeckType::Xl.reset_packet()))
+                    .times(1)
+                    .in_sequence(&mut sequence)
+                    .returning(|_data: &[u8]| Ok(()));
+                hid_device
+                    .expect_send_feature_report()
+                    .with(eq(StreamDeckType::Xl.brightness_packet(50)))
+                    .times(1)
+                    .in_sequence(&mut sequence)
+                    .returning(|_data: &[u8]| Ok(()));
+                hid_device
+                    .expect_write()
+                    .times(StreamDeckType::Xl.total_num_buttons())
+                    .in_sequence(&mut sequence)
+                    .returning(|data: &[u8]| Ok(data.len()));
+                Ok(hid_device)
+            });
 
         // Act
-        let devices = StreamDeckDevice::list_devices(&api_mock);
+        let device = StreamDeckDevice::open_ready(&api_mock, 50);
 
         // Test
-        assert_eq!(devices.len(), 0);
+        assert_eq!(device.unwrap().device_type, StreamDeckType::Xl);
     }
 
     #[test]
-    fn test_list_devices_non_streamdeck_empty() {
+    fn test_open_ready_default_brightness() {
         // Setup
         let mut api_mock = MockMockHidApi::new();
-        api_mock.expect_device_list().times(1).returning(|| {
+        api_mock.expect_device_list().returning(|| {
             let mut info_mock = MockDeviceInfoTrait::new();
-            info_mock.expect_vendor_id().returning(|| 1);
-            info_mock.expect_product_id().returning(|| 1);
+            info_mock
+                .expect_vendor_id()
+                .returning(|| StreamDeckType::Mini.get_vendor_id());
+            info_mock
+                .expect_product_id()
+                .returning(|| StreamDeckType::Mini.get_product_id());
+            info_mock
+                .expect_path()
+                .returning(|| String::from("/dev/hidraw0"));
+            info_mock.expect_serial_number().returning(|| None);
             Vec::from([info_mock])
         });
+        api_mock
+            .expect_open_path()
+            .times(1)
+            .returning(|_path: &str| {
+                let mut hid_device = MockHidDeviceTrait::new();
+                hid_device
+                    .expect_write()
+                    .returning(|data: &[u8]| Ok(data.len()));
+                hid_device
+                    .expect_send_feature_report()
+                    .with(eq(StreamDeckType::Mini.reset_packet()))
+                    .times(1)
+                    .returning(|_data: &[u8]| Ok(()));
+                hid_device
+                    .expect_send_feature_report()
+                    .with(eq(StreamDeckType::Mini
+                        .brightness_packet(StreamDeckType::Mini.default_brightness())))
+                    .times(1)
+                    .returning(|_data: &[u8]| Ok(()));
+                Ok(hid_device)
+            });
 
         // Act
-        let devices = StreamDeckDevice::list_devices(&api_mock);
+        let device = StreamDeckDevice::open_ready(&api_mock, None);
 
         // Test
-        assert_eq!(devices.len(), 0);
+        assert!(device.is_ok());
     }
 
     #[test]
-    fn test_list_devices_streamdeck_device() {
+    fn test_open_with_splash() {
         // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
         let mut api_mock = MockMockHidApi::new();
-        api_mock.expect_device_list().times(1).returning(|| {
-            let mut wrong_info_mock = MockDeviceInfoTrait::new();
-            wrong_info_mock.expect_vendor_id().returning(|| 1);
-            wrong_info_mock.expect_product_id().returning(|| 1);
-            let mut correct_info_mock = MockDeviceInfoTrait::new();
-            correct_info_mock
+        api_mock.expect_device_list().returning(|| {
+            let mut info_mock = MockDeviceInfoTrait::new();
+            info_mock
                 .expect_vendor_id()
-                .returning(|| StreamDeckType::Xl.get_vendor_id());
-            correct_info_mock
+                .returning(|| StreamDeckType::Orig.get_vendor_id());
+            info_mock
                 .expect_product_id()
-                .returning(|| StreamDeckType::Xl.get_product_id());
-            Vec::from([wrong_info_mock, correct_info_mock])
+                .returning(|| StreamDeckType::Orig.get_product_id());
+            info_mock
+                .expect_path()
+                .returning(|| String::from("/dev/hidraw0"));
+            info_mock.expect_serial_number().returning(|| None);
+            Vec::from([info_mock])
         });
+        let written_clone = written.clone();
+        api_mock
+            .expect_open_path()
+            .times(1)
+            .returning(move |_path: &str| {
+                let mut hid_device = MockHidDeviceTrait::new();
+                hid_device
+                    .expect_send_feature_report()
+                    .with(eq(StreamDeckType::Orig.reset_packet()))
+                    .times(1)
+                    .returning(|_data: &[u8]| Ok(()));
+                let written_clone = written_clone.clone();
+                hid_device.expect_write().returning(move |data: &[u8]| {
+                    written_clone.lock().unwrap().push(data.to_vec());
+                    Ok(data.len())
+                });
+                Ok(hid_device)
+            });
+        let splash = StreamDeckType::Xl.test_pattern(crate::TestPattern::Checker);
 
         // Act
-        let devices = StreamDeckDevice::list_devices(&api_mock);
+        let device = StreamDeckDevice::open_with_splash(&api_mock, &splash).unwrap();
 
         // Test
-        assert_eq!(devices.len(), 1);
-        assert_eq!(devices[0].0, StreamDeckType::Xl);
+        let resized = imageops::resize(&splash, 72, 72, imageops::FilterType::Triangle);
+        let mut expected = vec![StreamDeckType::Orig.reset_key_stream_packet()];
+        for button_id in 0..StreamDeckType::Orig.total_num_buttons() {
+            expected.extend(
+                device
+                    .image_packages(button_id as u8, &resized, &mut Vec::new())
+                    .unwrap(),
+            );
+        }
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_read_button_events_simultaneous() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Orig.button_read_offset()] = 1;
+                data[StreamDeckType::Orig.button_read_offset() + 5] = 1;
+                Ok(data.len())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+
+        // Act
+        let events = device.read_button_events().unwrap();
+
+        // Test
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].button_id, 0);
+        assert_eq!(events[0].state, ButtonState::Down);
+        assert_eq!(events[1].button_id, 5);
+        assert_eq!(events[1].state, ButtonState::Down);
+    }
+
+    #[test]
+    fn test_read_button_events_partial_read() {
+        // Setup
+        let offset = StreamDeckType::Orig.button_read_offset();
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut seq = Sequence::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|data: &mut [u8]| {
+                data.fill(1);
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |data: &mut [u8]| {
+                data[..offset + 5].fill(1);
+                data[offset] = 0;
+                Ok(offset + 5)
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+
+        // Act
+        let pressed = device.read_button_events().unwrap();
+        let events = device.read_button_events().unwrap();
+
+        // Test
+        assert_eq!(pressed.len(), 15);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].button_id, 0);
+        assert_eq!(events[0].state, ButtonState::Up);
+    }
+
+    #[test]
+    fn test_mirror_to() {
+        // Setup
+        let primary = mock_device(StreamDeckType::Xl);
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let secondary = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let image = StreamDeckType::Xl.test_pattern(crate::TestPattern::Gradient);
+
+        // Act
+        primary.set_button_image(2, &image).unwrap();
+        primary.mirror_to(&secondary).unwrap();
+
+        // Test
+        let resized = imageops::resize(&image, 72, 72, imageops::FilterType::Triangle);
+        let expected = crate::image::image_packages(StreamDeckType::Orig, &resized, 2).unwrap();
+        assert_eq!(*written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_button_image_no_transform() {
+        // Setup
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hid_device = MockHidDeviceTrait::new();
+        let written_clone = written.clone();
+        hid_device.expect_write().returning(move |data: &[u8]| {
+            written_clone.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Orig, hid_device);
+        let image = StreamDeckType::Orig.test_pattern(crate::TestPattern::Gradient);
+
+        // Act
+        device.set_button_image_no_transform(0, &image).unwrap();
+
+        // Test
+        let header_len = StreamDeckType::Orig.image_package_header(0, 0, 0).len();
+        let max_payload_size = StreamDeckType::Orig.max_payload_size();
+        let encoded_image: Vec<u8> = written
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|package| package[header_len..header_len + max_payload_size].to_vec())
+            .collect();
+        let decoded = image::load_from_memory(&encoded_image).unwrap().to_rgb8();
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn test_button_state_bitmask() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_read()
+            .times(1)
+            .returning(|data: &mut [u8]| {
+                data.fill(0);
+                data[StreamDeckType::Xl.button_read_offset()] = 1;
+                data[StreamDeckType::Xl.button_read_offset() + 3] = 1;
+                Ok(data.len())
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Xl, hid_device);
+
+        // Act
+        let bitmask = device.button_state_bitmask().unwrap();
+
+        // Test
+        assert_eq!(bitmask, 0b1001);
+    }
+
+    #[test]
+    fn test_keepalive() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_send_feature_report()
+            .with(eq(StreamDeckType::Mini.brightness_packet(40)))
+            .times(2)
+            .returning(|_data: &[u8]| Ok(()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+        device.set_brightness(40).unwrap();
+
+        // Act (the mock fails on any other feature report)
+        let result = device.keepalive();
+
+        // Test
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        let mut sequence = Sequence::new();
+        hid_device
+            .expect_get_feature_report()
+            .returning(|data: &mut [u8]| Ok(data.len()));
+        hid_device
+            .expect_read_timeout()
+            .with(always(), eq(HEARTBEAT_READ_TIMEOUT_MS))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|data: &mut [u8], _timeout: i32| {
+                data.fill(0);
+                data[StreamDeckType::Mini.button_read_offset() + 1] = 1;
+                Ok(data.len())
+            });
+        hid_device
+            .expect_read_timeout()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_data: &mut [u8], _timeout: i32| {
+                Err(hidapi::HidError::HidApiError {
+                    message: String::from("device disconnected"),
+                })
+            });
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let alive = device.heartbeat();
+        let lost = device.heartbeat();
+
+        // Test
+        assert!(alive.unwrap());
+        assert_eq!(device.last_button_states()[1], ButtonState::Down);
+        assert!(matches!(lost, Err(Error::HidError(_))));
+    }
+
+    #[test]
+    fn test_keepalive_without_brightness() {
+        // Setup
+        let mut hid_device = MockHidDeviceTrait::new();
+        hid_device
+            .expect_get_feature_report()
+            .times(1)
+            .returning(|data: &mut [u8]| Ok(data.len()));
+        let device = StreamDeckDevice::<MockMockHidApi>::new(StreamDeckType::Mini, hid_device);
+
+        // Act
+        let result = device.keepalive();
+
+        // Test
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_open_device_at() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        api_mock.expect_device_list().returning(move || {
+            let mut devices: Vec<MockDeviceInfoTrait> = ["B", "A"]
+                .into_iter()
+                .map(|serial| {
+                    let mut info_mock = MockDeviceInfoTrait::new();
+                    info_mock
+                        .expect_vendor_id()
+                        .returning(|| StreamDeckType::Mini.get_vendor_id());
+                    info_mock
+                        .expect_product_id()
+                        .returning(|| StreamDeckType::Mini.get_product_id());
+                    info_mock
+                        .expect_serial_number()
+                        .returning(move || Some(String::from(serial)));
+                    info_mock
+                        .expect_path()
+                        .returning(move || format!("/dev/hidraw-{}", serial));
+                    info_mock
+                })
+                .collect();
+            // Enumerate in a different order on every call
+            if calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % 2 == 1 {
+                devices.reverse();
+            }
+            devices
+        });
+        let opened = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let opened_clone = opened.clone();
+        api_mock.expect_open_path().returning(move |path: &str| {
+            opened_clone.lock().unwrap().push(String::from(path));
+            Ok(MockHidDeviceTrait::new())
+        });
+
+        // Act
+        for _ in 0..2 {
+            StreamDeckDevice::open_device_at(&api_mock, 0).unwrap();
+            StreamDeckDevice::open_device_at(&api_mock, 1).unwrap();
+        }
+        let missing = StreamDeckDevice::open_device_at(&api_mock, 2);
+
+        // Test
+        assert_eq!(
+            *opened.lock().unwrap(),
+            vec![
+                "/dev/hidraw-A",
+                "/dev/hidraw-B",
+                "/dev/hidraw-A",
+                "/dev/hidraw-B"
+            ]
+        );
+        assert!(matches!(missing, Err(Error::NoDeviceFound)));
+    }
+
+    #[test]
+    fn test_list_devices_sorted() {
+        // Setup
+        let devices = [
+            (Some("B"), "1-1.10:1.0"),
+            (None, "1-1.10:1.0"),
+            (Some("A"), "1-1.3:1.0"),
+            (None, "1-1.2:1.0"),
+            (Some("A"), "1-1.2:1.0"),
+        ];
+        let mut api_mock = MockMockHidApi::new();
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        api_mock.expect_device_list().returning(move || {
+            // Enumerate in a different order on every call
+            let mut order: Vec<_> = devices.to_vec();
+            order.rotate_left(calls.fetch_add(2, std::sync::atomic::Ordering::Relaxed) % 5);
+            order
+                .into_iter()
+                .map(|(serial, path)| {
+                    let mut info_mock = MockDeviceInfoTrait::new();
+                    info_mock
+                        .expect_vendor_id()
+                        .returning(|| StreamDeckType::Xl.get_vendor_id());
+                    info_mock
+                        .expect_product_id()
+                        .returning(|| StreamDeckType::Xl.get_product_id());
+                    info_mock
+                        .expect_serial_number()
+                        .returning(move || serial.map(String::from));
+                    info_mock
+                        .expect_path()
+                        .returning(move || String::from(path));
+                    info_mock
+                })
+                .collect()
+        });
+
+        // Act
+        let listings: Vec<Vec<(Option<String>, String)>> = (0..3)
+            .map(|_| {
+                StreamDeckDevice::list_devices_sorted(&api_mock)
+                    .iter()
+                    .map(|(_, info)| (info.serial_number(), info.path()))
+                    .collect()
+            })
+            .collect();
+
+        // Test
+        let expected: Vec<(Option<String>, String)> = vec![
+            (None, String::from("1-1.2:1.0")),
+            (None, String::from("1-1.10:1.0")),
+            (Some(String::from("A")), String::from("1-1.2:1.0")),
+            (Some(String::from("A")), String::from("1-1.3:1.0")),
+            (Some(String::from("B")), String::from("1-1.10:1.0")),
+        ];
+        for listing in listings {
+            assert_eq!(listing, expected);
+        }
     }
 }
@@ -6,4 +6,133 @@ pub enum Error {
     DimensionMismatch(u32, u32),
     ImageEncodingError(image::ImageError),
     IncorrectWriteLengthError,
+    Unsupported,
+    /// Writing an image packet failed, the image on the button is only partially drawn.
+    ///
+    /// `button_id` is `None` if the image was for the info screen.
+    WriteFailed {
+        button_id: Option<u8>,
+        packet_index: usize,
+        source: hidapi::HidError,
+    },
+    /// Opening the device failed on every attempt, e.g. because another program holds it.
+    ///
+    /// `source` is the error of the last attempt.
+    Busy {
+        source: hidapi::HidError,
+    },
+    /// An already encoded image is not in the image format of the device.
+    InvalidEncodedImage,
+    /// The device has no button with this name, see [StreamDeckType::named_buttons].
+    ///
+    /// [StreamDeckType::named_buttons]: crate::StreamDeckType::named_buttons
+    UnknownButtonName(String),
+    /// The image does not fit into the byte budget, even when encoded with the lowest quality.
+    ///
+    /// Also returned if an encoded image needs more packages than the device supports,
+    /// see [StreamDeckType::max_image_pages].
+    ///
+    /// [StreamDeckType::max_image_pages]: crate::StreamDeckType::max_image_pages
+    ImageTooLarge {
+        size: usize,
+        max_bytes: usize,
+    },
+    /// A package of an image package sequence has the wrong length or a malformed header.
+    InvalidPacket {
+        packet_index: usize,
+    },
+    /// Header and payload of an image package do not fit into the package size of the device.
+    PackageOverflow {
+        needed: usize,
+        package_size: usize,
+    },
+    /// An error of a specific device, see [StreamDeckDevice::set_error_context].
+    ///
+    /// `device` is the device type and serial number, like in the log messages.
+    ///
+    /// [StreamDeckDevice::set_error_context]: crate::StreamDeckDevice::set_error_context
+    Device {
+        device: String,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The error without the device context added by [Error::Device].
+    pub fn without_device(&self) -> &Error {
+        match self {
+            Error::Device { source, .. } => source.without_device(),
+            error => error,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotAStreamDeckDevice => write!(f, "not a Streamdeck device"),
+            Error::NoDeviceFound => write!(f, "no Streamdeck device found"),
+            Error::HidError(_) => write!(f, "hid error"),
+            Error::DimensionMismatch(width, height) => {
+                write!(f, "image has the wrong dimensions {}x{}", width, height)
+            }
+            Error::ImageEncodingError(_) => write!(f, "encoding the image failed"),
+            Error::IncorrectWriteLengthError => write!(f, "the device did not accept all data"),
+            Error::Unsupported => write!(f, "not supported by the device"),
+            Error::WriteFailed {
+                button_id: Some(button_id),
+                packet_index,
+                ..
+            } => write!(
+                f,
+                "writing packet {} of the image for button {} failed",
+                packet_index, button_id
+            ),
+            Error::WriteFailed {
+                button_id: None,
+                packet_index,
+                ..
+            } => write!(
+                f,
+                "writing packet {} of the image for the info screen failed",
+                packet_index
+            ),
+            Error::Busy { .. } => write!(f, "the device is busy"),
+            Error::InvalidEncodedImage => {
+                write!(f, "the encoded image is not in the format of the device")
+            }
+            Error::UnknownButtonName(name) => write!(f, "unknown button name {:?}", name),
+            Error::ImageTooLarge { size, max_bytes } => write!(
+                f,
+                "the image needs {} bytes, more than the budget of {} bytes",
+                size, max_bytes
+            ),
+            Error::InvalidPacket { packet_index } => {
+                write!(f, "image package {} is malformed", packet_index)
+            }
+            Error::PackageOverflow {
+                needed,
+                package_size,
+            } => write!(
+                f,
+                "image package needs {} bytes, but packages have {} bytes",
+                needed, package_size
+            ),
+            Error::Device { device, .. } => write!(f, "error of device {}", device),
+        }
+    }
+}
+
+// The wrapped errors are not part of the message, they are returned by `source`
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::HidError(error) => Some(error),
+            Error::ImageEncodingError(error) => Some(error),
+            Error::WriteFailed { source, .. } => Some(source),
+            Error::Busy { source } => Some(source),
+            Error::Device { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
@@ -8,6 +8,27 @@ use mockall::*;
 pub trait DeviceInfoTrait {
     fn vendor_id(&self) -> u16;
     fn product_id(&self) -> u16;
+    fn serial_number(&self) -> Option<String>;
+    fn interface_number(&self) -> i32;
+    fn path(&self) -> String;
+    fn manufacturer_string(&self) -> Option<String>;
+    fn product_string(&self) -> Option<String>;
+    fn bus_type(&self) -> hidapi::BusType;
+
+    /// Check if both infos refer to the same physical device, see [same_device].
+    fn same_device(&self, other: &Self) -> bool
+    where
+        Self: Sized,
+    {
+        same_device(self, other)
+    }
+}
+
+/// Check if two device infos refer to the same physical device.
+///
+/// Devices are the same, if they have the same serial number and path.
+pub fn same_device<D: DeviceInfoTrait>(device: &D, other: &D) -> bool {
+    device.serial_number() == other.serial_number() && device.path() == other.path()
 }
 
 impl DeviceInfoTrait for hidapi::DeviceInfo {
@@ -18,13 +39,39 @@ impl DeviceInfoTrait for hidapi::DeviceInfo {
     fn product_id(&self) -> u16 {
         self.product_id()
     }
+
+    fn serial_number(&self) -> Option<String> {
+        self.serial_number().map(String::from)
+    }
+
+    fn interface_number(&self) -> i32 {
+        self.interface_number()
+    }
+
+    fn path(&self) -> String {
+        self.path().to_string_lossy().into_owned()
+    }
+
+    fn manufacturer_string(&self) -> Option<String> {
+        self.manufacturer_string().map(String::from)
+    }
+
+    fn product_string(&self) -> Option<String> {
+        self.product_string().map(String::from)
+    }
+
+    fn bus_type(&self) -> hidapi::BusType {
+        self.bus_type()
+    }
 }
 
 #[automock]
 pub trait HidDeviceTrait {
     fn send_feature_report(&self, data: &[u8]) -> hidapi::HidResult<()>;
+    fn get_feature_report(&self, buf: &mut [u8]) -> hidapi::HidResult<usize>;
     fn write(&self, data: &[u8]) -> hidapi::HidResult<usize>;
     fn read(&self, buf: &mut [u8]) -> hidapi::HidResult<usize>;
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> hidapi::HidResult<usize>;
 }
 
 impl HidDeviceTrait for hidapi::HidDevice {
@@ -32,6 +79,10 @@ impl HidDeviceTrait for hidapi::HidDevice {
         self.send_feature_report(data)
     }
 
+    fn get_feature_report(&self, buf: &mut [u8]) -> hidapi::HidResult<usize> {
+        self.get_feature_report(buf)
+    }
+
     fn write(&self, data: &[u8]) -> hidapi::HidResult<usize> {
         self.write(data)
     }
@@ -39,6 +90,10 @@ impl HidDeviceTrait for hidapi::HidDevice {
     fn read(&self, buf: &mut [u8]) -> hidapi::HidResult<usize> {
         self.read(buf)
     }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> hidapi::HidResult<usize> {
+        self.read_timeout(buf, timeout)
+    }
 }
 
 pub trait HidApiTrait {
@@ -46,6 +101,14 @@ pub trait HidApiTrait {
     type HidDevice: HidDeviceTrait;
     fn device_list(&self) -> Vec<Self::DeviceInfo>;
     fn open(&self, vid: u16, pid: u16) -> hidapi::HidResult<Self::HidDevice>;
+    fn open_path(&self, path: &str) -> hidapi::HidResult<Self::HidDevice>;
+
+    /// Update the list of devices returned by `device_list`.
+    ///
+    /// The default does nothing, for implementations whose `device_list` is always current.
+    fn refresh_devices(&mut self) -> hidapi::HidResult<()> {
+        Ok(())
+    }
 }
 
 impl HidApiTrait for hidapi::HidApi {
@@ -62,6 +125,15 @@ impl HidApiTrait for hidapi::HidApi {
     fn open(&self, vid: u16, pid: u16) -> hidapi::HidResult<Self::HidDevice> {
         self.open(vid, pid)
     }
+    fn open_path(&self, path: &str) -> hidapi::HidResult<Self::HidDevice> {
+        let path = std::ffi::CString::new(path).map_err(|e| hidapi::HidError::HidApiError {
+            message: e.to_string(),
+        })?;
+        self.open_path(&path)
+    }
+    fn refresh_devices(&mut self) -> hidapi::HidResult<()> {
+        self.refresh_devices()
+    }
 }
 
 mock! {
@@ -73,6 +145,8 @@ mock! {
         type HidDevice = MockHidDeviceTrait;
         fn device_list(&self) -> Vec<MockDeviceInfoTrait>;
         fn open(&self, vid: u16, pid: u16) -> hidapi::HidResult<MockHidDeviceTrait>;
+        fn open_path(&self, path: &str) -> hidapi::HidResult<MockHidDeviceTrait>;
+        fn refresh_devices(&mut self) -> hidapi::HidResult<()>;
     }
 }
 
@@ -85,15 +159,60 @@ pub fn create_api_mock_for_examples() -> MockMockHidApi {
             .returning(|| StreamDeckType::Xl.get_vendor_id());
         di.expect_product_id()
             .returning(|| StreamDeckType::Xl.get_product_id());
+        di.expect_serial_number()
+            .returning(|| Some(String::from("EXAMPLE")));
+        di.expect_interface_number().returning(|| 0);
+        di.expect_path().returning(|| String::from("/dev/hidraw0"));
+        di.expect_manufacturer_string()
+            .returning(|| Some(String::from("Elgato")));
+        di.expect_product_string()
+            .returning(|| Some(String::from("Stream Deck XL")));
+        di.expect_bus_type().returning(|| hidapi::BusType::Usb);
         Vec::from([di])
     });
-    result.expect_open().returning(|_vid: u16, _pid: u16| {
-        let mut hd = MockHidDeviceTrait::new();
-        hd.expect_send_feature_report()
-            .returning(|_data: &[u8]| Ok(()));
-        hd.expect_write().returning(|data: &[u8]| Ok(data.len()));
-        hd.expect_read().returning(|data: &mut [u8]| Ok(data.len()));
-        Ok(hd)
-    });
     result
+        .expect_open()
+        .returning(|_vid: u16, _pid: u16| Ok(create_device_mock_for_examples()));
+    result
+        .expect_open_path()
+        .returning(|_path: &str| Ok(create_device_mock_for_examples()));
+    result
+}
+
+/// Create a mocked hid device, as opened by [create_api_mock_for_examples].
+fn create_device_mock_for_examples() -> MockHidDeviceTrait {
+    let mut hd = MockHidDeviceTrait::new();
+    hd.expect_send_feature_report()
+        .returning(|_data: &[u8]| Ok(()));
+    hd.expect_get_feature_report()
+        .returning(|data: &mut [u8]| Ok(data.len()));
+    hd.expect_write().returning(|data: &[u8]| Ok(data.len()));
+    hd.expect_read().returning(|data: &mut [u8]| Ok(data.len()));
+    hd.expect_read_timeout()
+        .returning(|data: &mut [u8], _timeout: i32| Ok(data.len()));
+    hd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info_mock(serial_number: &'static str, path: &'static str) -> MockDeviceInfoTrait {
+        let mut info_mock = MockDeviceInfoTrait::new();
+        info_mock
+            .expect_serial_number()
+            .returning(move || Some(String::from(serial_number)));
+        info_mock
+            .expect_path()
+            .returning(move || String::from(path));
+        info_mock
+    }
+
+    #[test]
+    fn test_same_device() {
+        let device = info_mock("A1", "/dev/hidraw0");
+        assert!(same_device(&device, &info_mock("A1", "/dev/hidraw0")));
+        assert!(!same_device(&device, &info_mock("B2", "/dev/hidraw0")));
+        assert!(!same_device(&device, &info_mock("A1", "/dev/hidraw1")));
+    }
 }
@@ -0,0 +1,187 @@
+//! Notifications about Streamdecks being plugged in or removed.
+//!
+//! hidapi can not report hotplug events itself, so the devices are enumerated
+//! regularly and the enumerations are compared.
+
+use crate::hid_api_traits::*;
+use crate::{Error, StreamDeckDevice, StreamDeckType};
+use std::time::Duration;
+
+/// Identifies a connected Streamdeck.
+///
+/// Two ids are equal if they refer to the same physical device, like [same_device]
+/// for device infos.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DeviceId {
+    /// The type of the Streamdeck.
+    pub device_type: StreamDeckType,
+    /// The serial number of the device, if available.
+    pub serial_number: Option<String>,
+    /// The platform specific path of the device.
+    pub path: String,
+}
+
+/// A change in the connected Streamdecks.
+#[derive(PartialEq, Debug, Clone)]
+pub enum HotplugEvent {
+    /// The device has been plugged in.
+    Arrived(DeviceId),
+    /// The device has been removed.
+    Left(DeviceId),
+}
+
+/// Finds the changes between enumerations of the Streamdeck devices.
+///
+/// The first call to [DeviceWatcher::poll] reports all connected devices as arrived.
+#[derive(Debug, Default)]
+pub struct DeviceWatcher {
+    known: Vec<DeviceId>,
+}
+
+impl DeviceWatcher {
+    /// Create a watcher, that does not know any device yet.
+    pub fn new() -> DeviceWatcher {
+        DeviceWatcher::default()
+    }
+
+    /// Enumerate the devices and return the changes since the last call.
+    ///
+    /// The device list of `api` must have been refreshed before, for
+    /// [hidapi::HidApi] with `refresh_devices`.
+    pub fn poll<API: HidApiTrait>(&mut self, api: &API) -> Vec<HotplugEvent> {
+        let current: Vec<DeviceId> = StreamDeckDevice::list_devices(api)
+            .into_iter()
+            .map(|(device_type, info)| DeviceId {
+                device_type,
+                serial_number: info.serial_number(),
+                path: info.path(),
+            })
+            .collect();
+        let mut events: Vec<HotplugEvent> = self
+            .known
+            .iter()
+            .filter(|device| !current.contains(device))
+            .cloned()
+            .map(HotplugEvent::Left)
+            .collect();
+        events.extend(
+            current
+                .iter()
+                .filter(|device| !self.known.contains(device))
+                .cloned()
+                .map(HotplugEvent::Arrived),
+        );
+        self.known = current;
+        events
+    }
+}
+
+impl DeviceId {
+    /// The numbers in the path of the device, see [port_path](crate::port_path).
+    ///
+    /// Sorting by these gives the same order of devices across reboots.
+    pub fn port_path(&self) -> Vec<u64> {
+        crate::port_path(&self.path)
+    }
+}
+
+impl<API: HidApiTrait> StreamDeckDevice<API> {
+    /// Watch for Streamdecks being plugged in or removed.
+    ///
+    /// The devices are enumerated every `interval` and the closure is called for every
+    /// change. Devices connected when calling this are reported as arrived first.
+    /// This only returns, if refreshing the device list fails.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use streamdeck_hid_rs::{HotplugEvent, StreamDeckDevice};
+    ///
+    /// fn main() {
+    ///     let mut hidapi = hidapi::HidApi::new().unwrap();
+    ///     # let mut hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+    ///
+    ///     // StreamDeckDevice::watch_devices(&mut hidapi, Duration::from_secs(1), |event| {
+    ///     //     match event {
+    ///     //         HotplugEvent::Arrived(id) => println!("{} plugged in", id.device_type.name()),
+    ///     //         HotplugEvent::Left(id) => println!("{} removed", id.device_type.name()),
+    ///     //     }
+    ///     // }).unwrap();
+    /// }
+    /// ```
+    pub fn watch_devices<F>(api: &mut API, interval: Duration, cb: F) -> Result<(), Error>
+    where
+        F: Fn(HotplugEvent),
+    {
+        let mut watcher = DeviceWatcher::new();
+        loop {
+            api.refresh_devices().map_err(Error::HidError)?;
+            for event in watcher.poll(api) {
+                cb(event);
+            }
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::Sequence;
+
+    fn device_list(paths: &'static [&'static str]) -> Vec<MockDeviceInfoTrait> {
+        paths
+            .iter()
+            .map(|path| {
+                let mut info_mock = MockDeviceInfoTrait::new();
+                info_mock
+                    .expect_vendor_id()
+                    .returning(|| StreamDeckType::Mini.get_vendor_id());
+                info_mock
+                    .expect_product_id()
+                    .returning(|| StreamDeckType::Mini.get_product_id());
+                info_mock.expect_serial_number().returning(|| None);
+                info_mock.expect_path().returning(|| String::from(*path));
+                info_mock
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_device_watcher_poll() {
+        // Setup
+        let mut api_mock = MockMockHidApi::new();
+        let mut sequence = Sequence::new();
+        api_mock
+            .expect_device_list()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| device_list(&["/dev/hidraw0"]));
+        api_mock
+            .expect_device_list()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| device_list(&["/dev/hidraw0", "/dev/hidraw1"]));
+        api_mock
+            .expect_device_list()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| device_list(&["/dev/hidraw1"]));
+        let mut watcher = DeviceWatcher::new();
+        let id = |path: &str| DeviceId {
+            device_type: StreamDeckType::Mini,
+            serial_number: None,
+            path: String::from(path),
+        };
+
+        // Act
+        let initial = watcher.poll(&api_mock);
+        let plugged_in = watcher.poll(&api_mock);
+        let removed = watcher.poll(&api_mock);
+
+        // Test
+        assert_eq!(initial, vec![HotplugEvent::Arrived(id("/dev/hidraw0"))]);
+        assert_eq!(plugged_in, vec![HotplugEvent::Arrived(id("/dev/hidraw1"))]);
+        assert_eq!(removed, vec![HotplugEvent::Left(id("/dev/hidraw0"))]);
+    }
+}
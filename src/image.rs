@@ -1,92 +1,697 @@
 //! Module to create packages for images send to streamdeck devices.
 
-use crate::ImageTransformation::{Rotate180, Rotate270};
-use crate::{Error, StreamDeckImageFormat, StreamDeckType};
+use crate::{Error, ImageTransformation, StreamDeckImageFormat, StreamDeckType};
 use image::codecs::bmp::BmpEncoder;
 use image::codecs::jpeg::JpegEncoder;
-use image::{imageops, ColorType, EncodableLayout, ImageResult, RgbImage};
-use std::cmp::min;
+use image::{imageops, EncodableLayout, ImageResult, Rgb, RgbImage};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
 
-/// Create an package from an image to send to a streamdeck device.
+/// Test patterns, that can be created with [StreamDeckType::test_pattern].
+#[derive(PartialEq, Debug, Clone)]
+pub enum TestPattern {
+    /// The whole image in one color.
+    SolidColor(Rgb<u8>),
+    /// A 4x4 checker board of white and black squares, starting with white in the top left.
+    Checker,
+    /// Red increases from left to right and green from top to bottom.
+    Gradient,
+}
+
+impl StreamDeckType {
+    /// Create a test pattern image in the button image size of the device.
+    ///
+    /// This gives a quick way to verify that uploading images works.
+    pub fn test_pattern(&self, kind: TestPattern) -> RgbImage {
+        let (width, height) = self.button_image_size();
+        match kind {
+            TestPattern::SolidColor(color) => RgbImage::from_pixel(width, height, color),
+            TestPattern::Checker => RgbImage::from_fn(width, height, |x, y| {
+                if (x * 4 / width + y * 4 / height) % 2 == 0 {
+                    Rgb([255, 255, 255])
+                } else {
+                    Rgb([0, 0, 0])
+                }
+            }),
+            TestPattern::Gradient => RgbImage::from_fn(width, height, |x, y| {
+                Rgb([
+                    (x * 255 / (width - 1)) as u8,
+                    (y * 255 / (height - 1)) as u8,
+                    0,
+                ])
+            }),
+        }
+    }
+}
+
+/// What to do with button images, that do not have the exact size expected by the device.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum DimensionPolicy {
+    /// Reject the image with [Error::DimensionMismatch].
+    #[default]
+    Strict,
+    /// Pad the image with black on the right and bottom, or crop it there,
+    /// to get the exact size.
+    AutoFit,
+}
+
+impl StreamDeckType {
+    /// Create a button image filled with the color of a gauge at `fraction`.
+    ///
+    /// The color goes from green (0.0) over yellow (0.5) to red (1.0), values outside
+    /// of this range are clamped. See [StreamDeckDevice::set_button_gauge].
+    ///
+    /// [StreamDeckDevice::set_button_gauge]: crate::StreamDeckDevice::set_button_gauge
+    pub fn gauge_image(&self, fraction: f32) -> RgbImage {
+        let (width, height) = self.button_image_size();
+        RgbImage::from_pixel(width, height, gauge_color(fraction))
+    }
+
+    /// Create a button image from raw RGB bytes, e.g. received from another process.
+    ///
+    /// The bytes are the rows of the image from top to bottom, with 3 bytes (red, green,
+    /// blue) per pixel. There must be exactly enough bytes for the button image size of the
+    /// device, otherwise [Error::DimensionMismatch] with the expected size is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckType;
+    ///
+    /// fn main() {
+    ///     let (width, height) = StreamDeckType::Mini.button_image_size();
+    ///     let bytes = vec![128; (width * height * 3) as usize];
+    ///     let image = StreamDeckType::Mini.image_from_rgb_bytes(&bytes).unwrap();
+    ///     assert_eq!(image.dimensions(), (width, height));
+    /// }
+    /// ```
+    pub fn image_from_rgb_bytes(&self, bytes: &[u8]) -> Result<RgbImage, Error> {
+        let (width, height) = self.button_image_size();
+        if bytes.len() != width as usize * height as usize * 3 {
+            return Err(Error::DimensionMismatch(width, height));
+        }
+        RgbImage::from_raw(width, height, bytes.to_vec())
+            .ok_or(Error::DimensionMismatch(width, height))
+    }
+}
+
+/// The color of a gauge, from green over yellow to red.
+fn gauge_color(fraction: f32) -> Rgb<u8> {
+    // NaN is treated as empty
+    let fraction = if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0)
+    };
+    let red = (fraction * 2.0).min(1.0);
+    let green = ((1.0 - fraction) * 2.0).min(1.0);
+    Rgb([
+        (red * 255.0).round() as u8,
+        (green * 255.0).round() as u8,
+        0,
+    ])
+}
+
+/// How the cached button images are compared with new images, to detect changes.
+///
+/// See [StreamDeckDevice::set_cache_hash_mode].
+///
+/// [StreamDeckDevice::set_cache_hash_mode]: crate::StreamDeckDevice::set_cache_hash_mode
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum CacheHashMode {
+    /// Compare every pixel. Changes are always detected.
+    #[default]
+    Full,
+    /// Compare a hash of every 8th row only. This is much cheaper for large images,
+    /// but changes only in the other rows are not detected, so the button keeps the
+    /// old image.
+    Fast,
+}
+
+/// Rows sampled by [fast_image_hash].
+const FAST_HASH_ROW_STEP: usize = 8;
+
+/// Hash the dimensions and every [FAST_HASH_ROW_STEP]th row of an image.
+pub(crate) fn fast_image_hash(image: &RgbImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    image.dimensions().hash(&mut hasher);
+    let row_len = image.width() as usize * 3;
+    if row_len > 0 {
+        for row in image.as_raw().chunks(row_len).step_by(FAST_HASH_ROW_STEP) {
+            row.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Multiply all pixel values with `factor` (clamped to [0, 1]), to make the image darker.
+pub(crate) fn dim_image(image: &RgbImage, factor: f32) -> RgbImage {
+    let factor = if factor.is_nan() {
+        0.0
+    } else {
+        factor.clamp(0.0, 1.0)
+    };
+    let mut dimmed = image.clone();
+    for value in dimmed.iter_mut() {
+        *value = (*value as f32 * factor).round() as u8;
+    }
+    dimmed
+}
+
+/// Multiply all pixel values with `factor` (at least 1), to make the image brighter.
+///
+/// Values are capped at 255, so black stays black and white stays white.
+pub(crate) fn brighten_image(image: &RgbImage, factor: f32) -> RgbImage {
+    let factor = if factor.is_nan() {
+        1.0
+    } else {
+        factor.max(1.0)
+    };
+    let mut brightened = image.clone();
+    for value in brightened.iter_mut() {
+        *value = (*value as f32 * factor).round().min(255.0) as u8;
+    }
+    brightened
+}
+
+impl ImageTransformation {
+    /// Apply the transformation to an image.
+    pub fn apply(&self, image: &RgbImage) -> RgbImage {
+        match *self {
+            ImageTransformation::None => image.clone(),
+            ImageTransformation::Rotate90 => imageops::rotate90(image),
+            ImageTransformation::Rotate180 => imageops::rotate180(image),
+            ImageTransformation::Rotate270 => imageops::rotate270(image),
+        }
+    }
+}
+
+/// Options changing how button images are prepared before sending them.
+#[derive(Debug, Clone, Default)]
+pub struct ImageOptions {
+    /// The image is already in the orientation of the device, skip the transformation.
+    pub native_orientation: bool,
+    /// How to handle images with the wrong dimensions.
+    pub dimension_policy: DimensionPolicy,
+    /// Quality (1 - 100) for jpeg encoded images, `None` for the best quality.
+    pub jpeg_quality: Option<u8>,
+}
+
+/// Quality used to encode jpeg images, if not given in the [ImageOptions].
+const DEFAULT_JPEG_QUALITY: u8 = 100;
+
+/// Create the packages to set a button image on a device of the given type.
+///
+/// This does not need an opened device, so packages can be created for devices that
+/// are not connected. The packages are the same [StreamDeckDevice::set_button_image]
+/// writes with the default options.
 ///
 /// # Arguments
 ///
 /// * 'device_type' - The type of Streamdeck device
 /// * 'image' - The image as an RGB image. Must be already in correct dimensions!
-/// * 'btn_index' - The index of the button for which the image shold be set.
+/// * 'button_id' - The id of the button for which the image should be set.
+///
+/// # Example
+/// ```
+/// use image::imageops::{resize, FilterType};
+/// use streamdeck_hid_rs::{pack_image_for, StreamDeckType};
+///
+/// fn main() {
+///     let (width, height) = StreamDeckType::Xl.button_image_size();
+///     let xl_image = image::RgbImage::new(width, height);
+///
+///     // Resize to the button size of the Mini first
+///     let (width, height) = StreamDeckType::Mini.button_image_size();
+///     let mini_image = resize(&xl_image, width, height, FilterType::Triangle);
+///     let packages = pack_image_for(StreamDeckType::Mini, &mini_image, 0).unwrap();
+///     assert!(!packages.is_empty());
+/// }
+/// ```
+///
+/// [StreamDeckDevice::set_button_image]: crate::StreamDeckDevice::set_button_image
+pub fn pack_image_for(
+    device_type: StreamDeckType,
+    image: &RgbImage,
+    button_id: u8,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut encoded_image = Vec::new();
+    image_packages_into(
+        device_type,
+        image,
+        button_id,
+        &ImageOptions::default(),
+        &mut encoded_image,
+    )
+}
+
+/// The id of a button and the packages to set its image, see [pack_all_buttons].
+pub type ButtonPackages = (u8, Vec<Vec<u8>>);
+
+/// Create the packages for the images of several buttons, grouped by button.
+///
+/// This is [pack_image_for] for a whole panel: the first image is for button 0, the
+/// second for button 1 and so on. Images beyond the number of buttons are ignored.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device
+/// * 'images' - The images as RGB images. Must be already in correct dimensions!
+///
+/// # Example
+/// ```
+/// use streamdeck_hid_rs::{pack_all_buttons, StreamDeckType, TestPattern};
+///
+/// fn main() {
+///     let image = StreamDeckType::Mini.test_pattern(TestPattern::Checker);
+///     let groups = pack_all_buttons(StreamDeckType::Mini, &[image.clone(), image]).unwrap();
+///     for (button_id, packages) in groups {
+///         println!("Button {} needs {} packages", button_id, packages.len());
+///     }
+/// }
+/// ```
+pub fn pack_all_buttons(
+    device_type: StreamDeckType,
+    images: &[RgbImage],
+) -> Result<Vec<ButtonPackages>, Error> {
+    let mut encoded_image = Vec::new();
+    images
+        .iter()
+        .take(device_type.total_num_buttons())
+        .enumerate()
+        .map(|(button_id, image)| {
+            let packages = image_packages_into(
+                device_type.clone(),
+                image,
+                button_id as u8,
+                &ImageOptions::default(),
+                &mut encoded_image,
+            )?;
+            Ok((button_id as u8, packages))
+        })
+        .collect()
+}
+
+/// Reassemble the encoded image from its button image packages, the reverse of [pack_image_for].
+///
+/// The packages are checked with [StreamDeckType::validate_packets], then the headers are
+/// stripped and the payloads concatenated. For devices using BMP images, the packages do not
+/// contain the payload size, so the padding of the last package is removed using the file
+/// size from the BMP header.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device the packages were created for.
+/// * 'packets' - The packages, in the order they would be written.
+///
+/// # Example
+/// ```
+/// use streamdeck_hid_rs::{pack_image_for, unpack_image_packets, StreamDeckType, TestPattern};
+///
+/// fn main() {
+///     let image = StreamDeckType::Xl.test_pattern(TestPattern::Gradient);
+///     let packages = pack_image_for(StreamDeckType::Xl, &image, 0).unwrap();
+///     let jpeg = unpack_image_packets(StreamDeckType::Xl, &packages).unwrap();
+///     assert!(StreamDeckType::Xl.is_valid_encoded_image(&jpeg));
+/// }
+/// ```
+pub fn unpack_image_packets(
+    device_type: StreamDeckType,
+    packets: &[Vec<u8>],
+) -> Result<Vec<u8>, Error> {
+    device_type.validate_packets(packets)?;
+    let header_size = device_type.image_package_header(0, 0, 0).len();
+    let mut encoded_image = Vec::new();
+    for packet in packets {
+        let payload_size = match device_type.button_image_format() {
+            StreamDeckImageFormat::Jpeg => packet[4] as usize | (packet[5] as usize) << 8,
+            StreamDeckImageFormat::Bmp => device_type.max_payload_size(),
+        };
+        encoded_image.extend_from_slice(&packet[header_size..header_size + payload_size]);
+    }
+    if device_type.button_image_format() == StreamDeckImageFormat::Bmp {
+        let file_size = encoded_image
+            .get(2..6)
+            .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            .filter(|file_size| *file_size <= encoded_image.len())
+            .ok_or(Error::InvalidPacket {
+                packet_index: packets.len().saturating_sub(1),
+            })?;
+        encoded_image.truncate(file_size);
+    }
+    Ok(encoded_image)
+}
+
+/// Create an package from an image to send to a streamdeck device, see [pack_image_for].
+#[cfg(test)]
 pub fn image_packages(
     device_type: StreamDeckType,
     image: &RgbImage,
     btn_index: u8,
 ) -> Result<Vec<Vec<u8>>, Error> {
-    // Check image dimensions
-    if image.width() != device_type.button_image_size().0
-        || image.height() != device_type.button_image_size().1
-    {
-        return Err(Error::DimensionMismatch(
-            device_type.button_image_size().0,
-            device_type.button_image_size().1,
-        ));
+    pack_image_for(device_type, image, btn_index)
+}
+
+/// Same as [image_packages], but encodes the image into a caller provided buffer.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device
+/// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+/// * 'btn_index' - The index of the button for which the image shold be set.
+/// * 'options' - Options for preparing the image.
+/// * 'encoded_image' - Scratch buffer for the encoded image. It is cleared and grows as needed.
+pub fn image_packages_into(
+    device_type: StreamDeckType,
+    image: &RgbImage,
+    btn_index: u8,
+    options: &ImageOptions,
+    encoded_image: &mut Vec<u8>,
+) -> Result<Vec<Vec<u8>>, Error> {
+    encode_button_image(&device_type, image, options, encoded_image)?;
+    button_packages(device_type, &*encoded_image, btn_index)?.collect()
+}
+
+/// Same as [image_packages], but the packages are created one at a time.
+///
+/// The image is encoded once, when calling this function. The packages are only
+/// created while iterating, so only one package is held in memory at a time.
+/// If encoding fails or the encoded image is too large, the only item is the error.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device
+/// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+/// * 'btn_index' - The index of the button for which the image shold be set.
+/// * 'options' - Options for preparing the image.
+pub fn image_packages_iter(
+    device_type: StreamDeckType,
+    image: &RgbImage,
+    btn_index: u8,
+    options: &ImageOptions,
+) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+    let mut encoded_image = Vec::new();
+    let (packages, error) =
+        match encode_button_image(&device_type, image, options, &mut encoded_image)
+            .and_then(|()| button_packages(device_type, encoded_image, btn_index))
+        {
+            Ok(packages) => (Some(packages), None),
+            Err(error) => (None, Some(error)),
+        };
+    error
+        .map(Err)
+        .into_iter()
+        .chain(packages.into_iter().flatten())
+}
+
+impl StreamDeckType {
+    /// Check, transform and encode a button image, writing the encoded bytes to `out`.
+    ///
+    /// The image is prepared the same way [StreamDeckDevice::set_button_image] does
+    /// with the default options, so the output is exactly what is sent to the device
+    /// (without the package headers).
+    ///
+    /// # Arguments
+    ///
+    /// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+    /// * 'out' - Where the encoded image is written to.
+    ///
+    /// [StreamDeckDevice::set_button_image]: crate::StreamDeckDevice::set_button_image
+    pub fn encode_button_image_to<W: Write>(
+        &self,
+        image: &RgbImage,
+        mut out: W,
+    ) -> Result<(), Error> {
+        encode_button_image_with(self, image, &ImageOptions::default(), &mut out)
     }
+}
+
+/// Pad (with black) or crop the image on the right and bottom to the given size.
+fn fit_to_size(image: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let mut fitted = RgbImage::new(width, height);
+    imageops::replace(&mut fitted, image, 0, 0);
+    fitted
+}
+
+/// Check, transform and encode a button image.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device
+/// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+/// * 'options' - Options for preparing the image.
+/// * 'encoded_image' - Buffer for the encoded image. It is cleared and grows as needed.
+pub(crate) fn encode_button_image(
+    device_type: &StreamDeckType,
+    image: &RgbImage,
+    options: &ImageOptions,
+    encoded_image: &mut Vec<u8>,
+) -> Result<(), Error> {
+    encoded_image.clear();
+    encode_button_image_with(device_type, image, options, encoded_image)?;
+    debug_assert!(
+        device_type.button_image_format() != StreamDeckImageFormat::Jpeg
+            || jpeg_frame_marker(encoded_image) == Some(JPEG_SOF_BASELINE),
+        "jpeg encoder produced a non baseline jpeg"
+    );
+    Ok(())
+}
+
+/// Same as [encode_button_image], but writes the encoded image to any writer.
+fn encode_button_image_with<W: Write>(
+    device_type: &StreamDeckType,
+    image: &RgbImage,
+    options: &ImageOptions,
+    out: &mut W,
+) -> Result<(), Error> {
+    // Check image dimensions
+    let (width, height) = device_type.button_image_size();
+    let fitted;
+    let image = if image.width() == width && image.height() == height {
+        image
+    } else {
+        match options.dimension_policy {
+            DimensionPolicy::Strict => return Err(Error::DimensionMismatch(width, height)),
+            DimensionPolicy::AutoFit => {
+                fitted = fit_to_size(image, width, height);
+                &fitted
+            }
+        }
+    };
 
     // Transform the image, depending on the deck type
-    let image = match device_type.button_image_transformation() {
-        Rotate180 => imageops::rotate180(image),
-        Rotate270 => imageops::rotate270(image),
+    let transformed;
+    let image = if options.native_orientation {
+        image
+    } else {
+        match device_type.button_image_transformation() {
+            ImageTransformation::None => image,
+            transformation => {
+                transformed = transformation.apply(image);
+                &transformed
+            }
+        }
     };
 
     // Encode the image!
-    let mut encoded_image = vec![0u8; 0];
     let encode_result = match device_type.button_image_format() {
-        StreamDeckImageFormat::Bmp => BmpEncoder::new(&mut encoded_image).encode(
+        StreamDeckImageFormat::Bmp => BmpEncoder::new(out).encode(
             image.as_bytes(),
             device_type.button_image_size().0,
             device_type.button_image_size().1,
-            ColorType::Rgb8,
+            device_type.color_type().into(),
         ),
-        StreamDeckImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut encoded_image, 100)
-            .encode(
-                image.as_bytes(),
-                device_type.button_image_size().0,
-                device_type.button_image_size().1,
-                ColorType::Rgb8,
-            ),
+        // The devices only decode baseline jpeg, which is all the JpegEncoder produces
+        StreamDeckImageFormat::Jpeg => {
+            JpegEncoder::new_with_quality(out, options.jpeg_quality.unwrap_or(DEFAULT_JPEG_QUALITY))
+                .encode(
+                    image.as_bytes(),
+                    device_type.button_image_size().0,
+                    device_type.button_image_size().1,
+                    device_type.color_type().into(),
+                )
+        }
     };
     if let ImageResult::Err(e) = encode_result {
         return Err(Error::ImageEncodingError(e));
     }
-    // The resulting list of packages
-    let mut result: Vec<Vec<u8>> = Vec::new();
+    Ok(())
+}
 
-    let mut bytes_remaining = encoded_image.len();
-    let mut page_number = 0;
+/// Start of frame marker of a baseline jpeg.
+pub(crate) const JPEG_SOF_BASELINE: u8 = 0xC0;
 
-    while bytes_remaining > 0 {
-        // Our current package
-        let mut package = vec![0; device_type.image_package_size()];
-        let payload_size = min(device_type.max_payload_size(), bytes_remaining);
+/// Find the start of frame (SOF) marker of a jpeg image.
+///
+/// The marker tells how the image is encoded, for example 0xC0 for baseline
+/// and 0xC2 for progressive jpeg. Returns `None` if no SOF marker is found.
+pub(crate) fn jpeg_frame_marker(jpeg: &[u8]) -> Option<u8> {
+    // Skip the start of image marker
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xFF {
+            return None;
+        }
+        match jpeg[pos + 1] {
+            // Define huffman table, jpeg extension and define arithmetic coding are no SOF markers
+            0xC4 | 0xC8 | 0xCC => {}
+            marker @ 0xC0..=0xCF => return Some(marker),
+            // Start of scan, the frame header has to come before
+            0xDA => return None,
+            _ => {}
+        }
+        let segment_length = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        pos += 2 + segment_length;
+    }
+    None
+}
 
-        let header = device_type.image_package_header(payload_size, btn_index, page_number);
-        // // Add the image header, but only on the first package
-        // let img_header = if page_number == 0 { image_header(t) } else { &[] };
+/// The packages for an encoded button image.
+///
+/// Returns [Error::ImageTooLarge], if the image needs more pages than the header can
+/// number.
+pub(crate) fn button_packages<E>(
+    device_type: StreamDeckType,
+    encoded_image: E,
+    btn_index: u8,
+) -> Result<impl Iterator<Item = Result<Vec<u8>, Error>>, Error>
+where
+    E: AsRef<[u8]>,
+{
+    let size = encoded_image.as_ref().len();
+    let max_bytes = device_type.max_image_pages() as usize * device_type.max_payload_size();
+    if size > max_bytes {
+        return Err(Error::ImageTooLarge { size, max_bytes });
+    }
+    Ok(packages(
+        encoded_image,
+        device_type.image_package_size(),
+        device_type.max_payload_size(),
+        move |payload_size, page_number| {
+            device_type.image_package_header(payload_size, btn_index, page_number)
+        },
+    ))
+}
 
+/// Split an encoded image into packages, each starting with a header.
+///
+/// # Arguments
+///
+/// * 'encoded_image' - The encoded image to split.
+/// * 'package_size' - The size of every package.
+/// * 'max_payload_size' - Maximum number of image bytes in a package.
+/// * 'header' - Creates the header from payload size and page number.
+fn packages<E, H>(
+    encoded_image: E,
+    package_size: usize,
+    max_payload_size: usize,
+    header: H,
+) -> impl Iterator<Item = Result<Vec<u8>, Error>>
+where
+    E: AsRef<[u8]>,
+    H: Fn(usize, u16) -> Vec<u8>,
+{
+    let num_pages = encoded_image.as_ref().len().div_ceil(max_payload_size);
+    (0..num_pages).map(move |page_number| {
+        let encoded_image = encoded_image.as_ref();
+        let start = page_number * max_payload_size;
+        let payload = &encoded_image[start..encoded_image.len().min(start + max_payload_size)];
+        let header = header(payload.len(), page_number as u16);
+        if header.len() + payload.len() > package_size {
+            return Err(Error::PackageOverflow {
+                needed: header.len() + payload.len(),
+                package_size,
+            });
+        }
+        let mut package = vec![0; package_size];
         package[..header.len()].copy_from_slice(&header);
-        // package[header.len() .. header.len() + img_header.len()].copy_from_slice(
-        //     img_header
-        // );
+        package[header.len()..header.len() + payload.len()].copy_from_slice(payload);
+        Ok(package)
+    })
+}
 
-        let taken_space = header.len(); // + img_header.len();
+/// Create the packages to set the image on the info screen of the Streamdeck Neo.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device, must have an info screen.
+/// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+pub fn info_image_packages(
+    device_type: StreamDeckType,
+    image: &RgbImage,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let (width, height) = device_type
+        .neo_info_image_size()
+        .ok_or(Error::Unsupported)?;
+    if image.width() != width || image.height() != height {
+        return Err(Error::DimensionMismatch(width, height));
+    }
 
-        let bytes_send = encoded_image.len() - bytes_remaining;
-        package[taken_space..taken_space + payload_size]
-            .copy_from_slice(&encoded_image[bytes_send..bytes_send + payload_size]);
+    // The info screen is mounted upside down, like the keys
+    let image = imageops::rotate180(image);
+    let mut encoded_image = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded_image, 100)
+        .encode(
+            image.as_bytes(),
+            width,
+            height,
+            device_type.color_type().into(),
+        )
+        .map_err(Error::ImageEncodingError)?;
 
-        bytes_remaining -= payload_size;
-        page_number += 1;
+    let max_payload_size = device_type.max_payload_size();
+    let encoded_len = encoded_image.len();
+    packages(
+        encoded_image,
+        device_type.image_package_size(),
+        max_payload_size,
+        |payload_size, page_number| {
+            let is_last = (page_number as usize + 1) * max_payload_size >= encoded_len;
+            device_type.info_image_package_header(payload_size, is_last, page_number)
+        },
+    )
+    .collect()
+}
 
-        result.push(package);
+/// Create the packages to set the whole touchscreen of the Streamdeck Plus.
+///
+/// # Arguments
+///
+/// * 'device_type' - The type of Streamdeck device, must have a touchscreen.
+/// * 'image' - The image as an RGB image. Must be already in correct dimensions!
+pub fn touchscreen_image_packages(
+    device_type: StreamDeckType,
+    image: &RgbImage,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let (width, height) = device_type.touchscreen_size().ok_or(Error::Unsupported)?;
+    if image.width() != width || image.height() != height {
+        return Err(Error::DimensionMismatch(width, height));
     }
-    Ok(result)
+
+    let mut encoded_image = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded_image, 100)
+        .encode(
+            image.as_bytes(),
+            width,
+            height,
+            device_type.color_type().into(),
+        )
+        .map_err(Error::ImageEncodingError)?;
+
+    // The touchscreen header is longer than the one for button images
+    let region = (0, 0, width as u16, height as u16);
+    let max_payload_size = device_type.image_package_size() - 16;
+    let encoded_len = encoded_image.len();
+    packages(
+        encoded_image,
+        device_type.image_package_size(),
+        max_payload_size,
+        |payload_size, page_number| {
+            let is_last = (page_number as usize + 1) * max_payload_size >= encoded_len;
+            device_type.touchscreen_image_package_header(region, payload_size, is_last, page_number)
+        },
+    )
+    .collect()
 }
 
 mod tests {
@@ -117,6 +722,413 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_image_packer_into_reuses_buffer() {
+        let mut scratch = Vec::new();
+        for device_type in StreamDeckType::ALL {
+            let image = image::RgbImage::new(
+                device_type.button_image_size().0,
+                device_type.button_image_size().1,
+            );
+            let expected = image_packages(device_type.clone(), &image, 1).unwrap();
+            let packages = image_packages_into(
+                device_type,
+                &image,
+                1,
+                &ImageOptions::default(),
+                &mut scratch,
+            )
+            .unwrap();
+            assert_eq!(packages, expected);
+        }
+    }
+
+    #[test]
+    fn test_image_packer_native_orientation() {
+        let options = ImageOptions {
+            native_orientation: true,
+            ..ImageOptions::default()
+        };
+        let mut image = image::RgbImage::new(72, 72);
+        image.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        image.put_pixel(10, 3, image::Rgb([0, 255, 0]));
+        let mut encoded_image = Vec::new();
+        image_packages_into(
+            StreamDeckType::Orig,
+            &image,
+            1,
+            &options,
+            &mut encoded_image,
+        )
+        .unwrap();
+
+        let decoded = image::load_from_memory(&encoded_image).unwrap().to_rgb8();
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn test_test_pattern() {
+        for device_type in StreamDeckType::ALL {
+            let (width, height) = device_type.button_image_size();
+
+            let solid = device_type.test_pattern(TestPattern::SolidColor(Rgb([1, 2, 3])));
+            assert_eq!(solid.dimensions(), (width, height));
+            assert_eq!(*solid.get_pixel(0, 0), Rgb([1, 2, 3]));
+            assert_eq!(*solid.get_pixel(width - 1, height - 1), Rgb([1, 2, 3]));
+
+            let checker = device_type.test_pattern(TestPattern::Checker);
+            assert_eq!(checker.dimensions(), (width, height));
+            assert_eq!(*checker.get_pixel(0, 0), Rgb([255, 255, 255]));
+            assert_eq!(*checker.get_pixel(width - 1, 0), Rgb([0, 0, 0]));
+            assert_eq!(
+                *checker.get_pixel(width - 1, height - 1),
+                Rgb([255, 255, 255])
+            );
+
+            let gradient = device_type.test_pattern(TestPattern::Gradient);
+            assert_eq!(gradient.dimensions(), (width, height));
+            assert_eq!(*gradient.get_pixel(0, 0), Rgb([0, 0, 0]));
+            assert_eq!(*gradient.get_pixel(width - 1, 0), Rgb([255, 0, 0]));
+            assert_eq!(
+                *gradient.get_pixel(width - 1, height - 1),
+                Rgb([255, 255, 0])
+            );
+        }
+    }
+
+    #[test]
+    fn test_dimension_policy_auto_fit() {
+        let options = ImageOptions {
+            dimension_policy: DimensionPolicy::AutoFit,
+            ..ImageOptions::default()
+        };
+        let narrow = RgbImage::from_pixel(95, 96, Rgb([255, 255, 255]));
+        let mut padded = RgbImage::from_pixel(96, 96, Rgb([255, 255, 255]));
+        for y in 0..96 {
+            padded.put_pixel(95, y, Rgb([0, 0, 0]));
+        }
+        let wide = RgbImage::from_pixel(97, 96, Rgb([255, 255, 255]));
+        let cropped = RgbImage::from_pixel(96, 96, Rgb([255, 255, 255]));
+
+        let mut encoded_image = Vec::new();
+        assert_eq!(
+            image_packages_into(StreamDeckType::Xl, &narrow, 0, &options, &mut encoded_image)
+                .unwrap(),
+            image_packages(StreamDeckType::Xl, &padded, 0).unwrap()
+        );
+        assert_eq!(
+            image_packages_into(StreamDeckType::Xl, &wide, 0, &options, &mut encoded_image)
+                .unwrap(),
+            image_packages(StreamDeckType::Xl, &cropped, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_image_packer_iter() {
+        for device_type in StreamDeckType::ALL {
+            let image = device_type.test_pattern(TestPattern::Gradient);
+            let expected = image_packages(device_type.clone(), &image, 2).unwrap();
+            let packages: Vec<Vec<u8>> =
+                image_packages_iter(device_type, &image, 2, &ImageOptions::default())
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+            assert_eq!(packages, expected);
+        }
+
+        let mut packages = image_packages_iter(
+            StreamDeckType::Xl,
+            &image::RgbImage::new(1, 1),
+            0,
+            &ImageOptions::default(),
+        );
+        assert!(matches!(
+            packages.next(),
+            Some(Err(Error::DimensionMismatch(96, 96)))
+        ));
+        assert!(packages.next().is_none());
+    }
+
+    #[test]
+    fn test_image_from_rgb_bytes() {
+        for device_type in StreamDeckType::ALL {
+            let (width, height) = device_type.button_image_size();
+            let length = (width * height * 3) as usize;
+            let bytes: Vec<u8> = (0..length).map(|index| index as u8).collect();
+
+            let image = device_type.image_from_rgb_bytes(&bytes).unwrap();
+            assert_eq!(image.dimensions(), (width, height));
+            assert_eq!(image.as_raw(), &bytes);
+
+            assert!(matches!(
+                device_type.image_from_rgb_bytes(&bytes[..length - 1]),
+                Err(Error::DimensionMismatch(w, h)) if w == width && h == height
+            ));
+            assert!(matches!(
+                device_type.image_from_rgb_bytes(&[bytes.as_slice(), &[0, 0, 0]].concat()),
+                Err(Error::DimensionMismatch(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_gauge_image() {
+        for device_type in StreamDeckType::ALL {
+            let (width, height) = device_type.button_image_size();
+            let center = |fraction: f32| {
+                *device_type
+                    .gauge_image(fraction)
+                    .get_pixel(width / 2, height / 2)
+            };
+            assert_eq!(device_type.gauge_image(0.0).dimensions(), (width, height));
+            assert_eq!(center(0.0), Rgb([0, 255, 0]));
+            assert_eq!(center(0.5), Rgb([255, 255, 0]));
+            assert_eq!(center(1.0), Rgb([255, 0, 0]));
+        }
+        assert_eq!(gauge_color(0.25), Rgb([128, 255, 0]));
+        assert_eq!(gauge_color(-1.0), Rgb([0, 255, 0]));
+        assert_eq!(gauge_color(2.0), Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn test_pack_all_buttons() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let (width, height) = device_type.button_image_size();
+            let images = vec![RgbImage::new(width, height); 3];
+
+            // Act
+            let groups = pack_all_buttons(device_type.clone(), &images).unwrap();
+
+            // Test
+            assert_eq!(groups.len(), images.len());
+            for (index, (button_id, packages)) in groups.iter().enumerate() {
+                assert_eq!(*button_id as usize, index);
+                assert!(device_type.validate_packets(packages).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_button_packages_too_many_pages() {
+        // Setup
+        let max_bytes = 255 * StreamDeckType::Mini.max_payload_size();
+        let fitting = vec![0u8; max_bytes];
+        let too_large = vec![0u8; max_bytes + 1];
+
+        // Act
+        let fitting_packages: Result<Vec<_>, _> =
+            button_packages(StreamDeckType::Mini, &fitting, 0)
+                .unwrap()
+                .collect();
+        let too_large_packages = button_packages(StreamDeckType::Mini, &too_large, 0);
+
+        // Test
+        assert_eq!(fitting_packages.unwrap().len(), 255);
+        assert!(matches!(
+            too_large_packages,
+            Err(Error::ImageTooLarge { size, max_bytes: max }) if size == max_bytes + 1 && max == max_bytes
+        ));
+    }
+
+    #[test]
+    fn test_unpack_image_packets() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let image = device_type.test_pattern(TestPattern::Gradient);
+            let mut encoded = Vec::new();
+            device_type
+                .encode_button_image_to(&image, &mut encoded)
+                .unwrap();
+            let packages = pack_image_for(device_type.clone(), &image, 2).unwrap();
+
+            // Act
+            let unpacked = unpack_image_packets(device_type.clone(), &packages).unwrap();
+
+            // Test
+            assert_eq!(unpacked, encoded, "{:?}", device_type);
+        }
+    }
+
+    #[test]
+    fn test_unpack_image_packets_malformed() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let image = device_type.test_pattern(TestPattern::Gradient);
+            let packages = pack_image_for(device_type.clone(), &image, 2).unwrap();
+            let modified = |modify: &dyn Fn(&mut Vec<u8>)| -> Vec<Vec<u8>> {
+                let mut packages = packages.clone();
+                modify(&mut packages[0]);
+                packages
+            };
+            let mut malformed = vec![
+                // Zeroed header
+                modified(&|packet| packet[..8].fill(0)),
+                // Button that does not exist
+                modified(&|packet| packet[2] = 0xFF),
+                // Truncated package
+                modified(&|packet| packet.truncate(10)),
+                // Payload length larger than the package
+                modified(&|packet| packet[4..6].fill(0xFF)),
+            ];
+            if device_type.button_image_format() == StreamDeckImageFormat::Bmp {
+                // Zeroed button byte, the button is stored plus one
+                malformed.push(modified(&|packet| packet[5] = 0));
+            }
+
+            for packets in malformed {
+                // Act
+                let result = unpack_image_packets(device_type.clone(), &packets);
+
+                // Test
+                assert!(
+                    matches!(result, Err(Error::InvalidPacket { .. })),
+                    "{:?}",
+                    device_type
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_image_hash() {
+        let black = RgbImage::new(96, 96);
+        let mut dot = black.clone();
+        dot.put_pixel(50, 16, Rgb([255, 255, 255]));
+        let gradient = StreamDeckType::Xl.test_pattern(TestPattern::Gradient);
+        let checker = StreamDeckType::Xl.test_pattern(TestPattern::Checker);
+
+        assert_eq!(fast_image_hash(&black), fast_image_hash(&black.clone()));
+        assert_ne!(fast_image_hash(&black), fast_image_hash(&dot));
+        assert_ne!(fast_image_hash(&gradient), fast_image_hash(&checker));
+        assert_ne!(
+            fast_image_hash(&black),
+            fast_image_hash(&RgbImage::new(72, 72))
+        );
+    }
+
+    #[test]
+    fn test_brighten_image() {
+        let image = RgbImage::from_pixel(9, 9, Rgb([200, 100, 0]));
+        assert_eq!(
+            *brighten_image(&image, 1.5).get_pixel(4, 4),
+            Rgb([255, 150, 0])
+        );
+        assert_eq!(brighten_image(&image, 0.5), image);
+        assert_eq!(brighten_image(&image, f32::NAN), image);
+    }
+
+    #[test]
+    fn test_dim_image() {
+        let image = RgbImage::from_pixel(9, 9, Rgb([200, 100, 50]));
+        let luminance = |image: &RgbImage| {
+            let Rgb([r, g, b]) = *image.get_pixel(4, 4);
+            0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32
+        };
+        let full = dim_image(&image, 1.0);
+        let half = dim_image(&image, 0.5);
+        assert_eq!(full, image);
+        assert_eq!(*half.get_pixel(4, 4), Rgb([100, 50, 25]));
+        assert!((luminance(&half) - luminance(&full) / 2.0).abs() < 1.0);
+        assert_eq!(dim_image(&image, 2.0), image);
+        assert_eq!(*dim_image(&image, -1.0).get_pixel(4, 4), Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_image_transformation_apply_inverse() {
+        // Asymmetric, so that every rotation gives a different image
+        let image = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8, y as u8, 0]));
+        for transformation in [
+            ImageTransformation::None,
+            ImageTransformation::Rotate90,
+            ImageTransformation::Rotate180,
+            ImageTransformation::Rotate270,
+        ] {
+            let transformed = transformation.apply(&image);
+            assert_eq!(transformation.inverse().apply(&transformed), image);
+        }
+        for device_type in StreamDeckType::ALL {
+            let displayed = device_type.image_spec().transformation.apply(&image);
+            assert_eq!(
+                device_type.read_back_transformation().apply(&displayed),
+                image
+            );
+        }
+    }
+
+    #[test]
+    fn test_typical_packet_count_is_plausible() {
+        for device_type in StreamDeckType::ALL {
+            let gradient = device_type.test_pattern(TestPattern::Gradient);
+            let packages = image_packages(device_type.clone(), &gradient, 0).unwrap();
+            assert!(packages.len() <= device_type.typical_packet_count());
+        }
+
+        let solid = StreamDeckType::Xl.test_pattern(TestPattern::SolidColor(Rgb([0, 0, 255])));
+        let packages = image_packages(StreamDeckType::Xl, &solid, 0).unwrap();
+        assert_eq!(packages.len(), 1);
+    }
+
+    #[test]
+    fn test_typical_packet_count_noise() {
+        for device_type in StreamDeckType::ALL {
+            // Setup (the worst case for jpeg, every pixel random)
+            let (width, height) = device_type.button_image_size();
+            let mut state = 0x2545_f491_u32;
+            let noise = RgbImage::from_fn(width, height, |_, _| {
+                Rgb([0; 3].map(|_: u8| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                }))
+            });
+
+            // Act
+            let packages = image_packages(device_type.clone(), &noise, 0).unwrap();
+
+            // Test
+            match device_type.button_image_format() {
+                StreamDeckImageFormat::Bmp => {
+                    assert_eq!(packages.len(), device_type.typical_packet_count())
+                }
+                // Only a heuristic for jpeg, noise does not fit
+                StreamDeckImageFormat::Jpeg => {
+                    assert!(packages.len() > device_type.typical_packet_count())
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_jpeg_is_baseline() {
+        for device_type in StreamDeckType::ALL {
+            if device_type.button_image_format() == StreamDeckImageFormat::Jpeg {
+                let image = device_type.test_pattern(TestPattern::Gradient);
+                let mut encoded_image = Vec::new();
+                encode_button_image(
+                    &device_type,
+                    &image,
+                    &ImageOptions::default(),
+                    &mut encoded_image,
+                )
+                .unwrap();
+                assert_eq!(jpeg_frame_marker(&encoded_image), Some(JPEG_SOF_BASELINE));
+            }
+        }
+    }
+
+    #[test]
+    fn test_jpeg_frame_marker() {
+        // SOI, APP0 with 2 bytes of data, SOF2 (progressive)
+        let progressive = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC2, 0x00, 0x02,
+        ];
+        assert_eq!(jpeg_frame_marker(&progressive), Some(0xC2));
+        // SOI, DHT, SOS without any SOF
+        let no_frame = [0xFF, 0xD8, 0xFF, 0xC4, 0x00, 0x02, 0xFF, 0xDA, 0x00, 0x02];
+        assert_eq!(jpeg_frame_marker(&no_frame), None);
+    }
+
     #[test]
     fn test_image_packer_header() {
         for device_type in StreamDeckType::ALL {
@@ -131,6 +1143,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encode_button_image_to() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let (width, height) = device_type.button_image_size();
+            let image = RgbImage::new(width, height);
+            let mut out = Vec::new();
+
+            // Act
+            device_type
+                .encode_button_image_to(&image, &mut out)
+                .unwrap();
+
+            // Test
+            assert!(out.starts_with(device_type.button_image_format().magic_bytes()));
+            let mut encoded_image = Vec::new();
+            encode_button_image(
+                &device_type,
+                &image,
+                &ImageOptions::default(),
+                &mut encoded_image,
+            )
+            .unwrap();
+            assert_eq!(out, encoded_image);
+        }
+    }
+
     #[test]
     fn test_image_packer_encoding() {
         for device_type in StreamDeckType::ALL {
@@ -142,16 +1181,11 @@ mod tests {
             let packages = image_packages(device_type.clone(), &image, 1).unwrap();
 
             // We just test if the first bytes are correctly set
-            match &device_type.button_image_format() {
-                StreamDeckImageFormat::Bmp => {
-                    assert_eq!(packages[0][correct_header.len()], 66);
-                    assert_eq!(packages[0][correct_header.len() + 1], 77);
-                }
-                StreamDeckImageFormat::Jpeg => {
-                    assert_eq!(packages[0][correct_header.len()], 255);
-                    assert_eq!(packages[0][correct_header.len() + 1], 216);
-                }
-            }
+            let magic_bytes = device_type.button_image_format().magic_bytes();
+            assert_eq!(
+                &packages[0][correct_header.len()..correct_header.len() + magic_bytes.len()],
+                magic_bytes
+            );
         }
     }
 
@@ -178,7 +1212,72 @@ mod tests {
                 StreamDeckType::Mini => {
                     assert_eq!(packages.len(), 3)
                 }
+                StreamDeckType::Plus => {
+                    assert_eq!(packages.len(), 2)
+                }
+                StreamDeckType::Neo => {
+                    assert_eq!(packages.len(), 1)
+                }
             }
         }
     }
+
+    #[test]
+    fn test_touchscreen_image_packages() {
+        // Setup
+        let image = image::RgbImage::new(800, 100);
+
+        // Act
+        let packages = touchscreen_image_packages(StreamDeckType::Plus, &image).unwrap();
+
+        // Test
+        assert!(packages.iter().all(|package| package.len() == 1024));
+        assert_eq!(
+            &packages[0][..10],
+            &[0x02, 0x0c, 0, 0, 0, 0, 0x20, 0x03, 100, 0]
+        );
+        assert_eq!(&packages[0][16..18], &[0xFF, 0xD8]);
+        assert_eq!(packages.last().unwrap()[10], 0x01);
+        assert!(matches!(
+            touchscreen_image_packages(StreamDeckType::Neo, &image),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_info_image_packages() {
+        let image = image::RgbImage::new(248, 58);
+        let packages = info_image_packages(StreamDeckType::Neo, &image).unwrap();
+        assert_eq!(packages[0][..2], [0x02, 0x0b]);
+        assert_eq!(packages.last().unwrap()[3], 0x01);
+
+        assert!(matches!(
+            info_image_packages(StreamDeckType::Neo, &image::RgbImage::new(96, 96)),
+            Err(Error::DimensionMismatch(248, 58))
+        ));
+        assert!(matches!(
+            info_image_packages(StreamDeckType::Xl, &image),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_packages_header_too_large() {
+        let encoded_image = vec![1u8; 100];
+        let result: Result<Vec<Vec<u8>>, Error> =
+            packages(&encoded_image, 64, 60, |_, _| vec![0; 8]).collect();
+        assert!(matches!(
+            result,
+            Err(Error::PackageOverflow {
+                needed: 68,
+                package_size: 64
+            })
+        ));
+
+        let result: Result<Vec<Vec<u8>>, Error> =
+            packages(&encoded_image, 64, 56, |_, _| vec![0; 8]).collect();
+        let result = result.unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[1][8..52], [1u8; 44]);
+    }
 }
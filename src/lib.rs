@@ -1,13 +1,25 @@
 // Publicly expose the modules
+mod button_dispatcher;
 mod device;
 mod error;
 pub mod hid_api_traits;
+#[cfg(feature = "hotplug")]
+mod hotplug;
 mod image;
 mod type_info;
+mod upload_governor;
 
+pub use crate::image::{
+    pack_all_buttons, pack_image_for, unpack_image_packets, ButtonPackages, CacheHashMode,
+    DimensionPolicy, TestPattern,
+};
+pub use button_dispatcher::*;
 pub use device::*;
 pub use error::*;
+#[cfg(feature = "hotplug")]
+pub use hotplug::*;
 pub use type_info::*;
+pub use upload_governor::*;
 
 #[cfg(test)]
 mod tests {
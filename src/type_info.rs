@@ -5,7 +5,10 @@
 //!
 //! The type of the streamdeck is defined in the enum [StreamDeckType]
 
+use crate::Error;
+use image::ColorType;
 use std::cmp::min;
+use std::sync::Mutex;
 
 /// Type of Streamdeck device.
 ///
@@ -16,6 +19,8 @@ pub enum StreamDeckType {
     OrigV2,
     Orig,
     Mini,
+    Plus,
+    Neo,
 }
 
 /// The image formats a Streamdeck can use.
@@ -28,24 +33,122 @@ pub enum StreamDeckImageFormat {
     Bmp,
 }
 
+impl StreamDeckImageFormat {
+    /// The bytes every encoded image in this format starts with.
+    pub fn magic_bytes(&self) -> &'static [u8] {
+        match *self {
+            StreamDeckImageFormat::Jpeg => &[0xFF, 0xD8],
+            StreamDeckImageFormat::Bmp => &[0x42, 0x4D],
+        }
+    }
+}
+
 /// The transformation an image needs to make to be correctly displayed on the screen.
 ///
-/// This enum contains only those transformations ever needed on streamdecks.
+/// This enum contains only those transformations ever needed on streamdecks,
+/// and their inverses.
 #[derive(PartialEq, Debug)]
 pub enum ImageTransformation {
+    None,
+    Rotate90,
     Rotate180,
     Rotate270,
 }
 
+impl ImageTransformation {
+    /// The transformation undoing this transformation.
+    pub fn inverse(&self) -> ImageTransformation {
+        match *self {
+            ImageTransformation::None => ImageTransformation::None,
+            ImageTransformation::Rotate90 => ImageTransformation::Rotate270,
+            ImageTransformation::Rotate180 => ImageTransformation::Rotate180,
+            ImageTransformation::Rotate270 => ImageTransformation::Rotate90,
+        }
+    }
+}
+
+/// An element on a Streamdeck, the user can interact with.
+#[derive(PartialEq, Debug, Clone)]
+pub enum InputElement {
+    /// A key with a display, at the given row and column of the key grid.
+    Key { index: u32, row: u32, column: u32 },
+    /// A rotary encoder (dial), at the given column below the keys.
+    Encoder { index: u32, column: u32 },
+    /// A touch screen with the given size in pixels.
+    TouchScreen { width: u32, height: u32 },
+}
+
+/// Full description of the images a Streamdeck expects for its buttons.
+///
+/// This bundles everything needed to produce images for a device type with
+/// an external encoder.
+#[derive(PartialEq, Debug)]
+pub struct ImageSpec {
+    /// The image format the device decodes.
+    pub format: StreamDeckImageFormat,
+    /// The color type of the raw image before encoding (no alpha channel is accepted).
+    pub color_type: ColorType,
+    /// Expected width and height of the image.
+    pub size: (u32, u32),
+    /// Transformation to apply before encoding.
+    pub transformation: ImageTransformation,
+}
+
+/// Everything a user interface needs to know about a type of Streamdeck.
+///
+/// Created with [StreamDeckType::capabilities].
+#[derive(PartialEq, Debug)]
+pub struct DeviceCapabilities {
+    /// The type of the Streamdeck.
+    pub device_type: StreamDeckType,
+    /// Human readable name, see [StreamDeckType::name].
+    pub name: &'static str,
+    /// Rows and columns of buttons, see [StreamDeckType::num_buttons].
+    pub num_buttons: (u32, u32),
+    /// All input elements, see [StreamDeckType::input_elements].
+    pub input_elements: Vec<InputElement>,
+    /// Description of the button images, see [StreamDeckType::image_spec].
+    pub image_spec: ImageSpec,
+    /// Size of the info screen, if there is one, see [StreamDeckType::neo_info_image_size].
+    pub info_image_size: Option<(u32, u32)>,
+}
+
+/// Result of recognizing a device by vendor and product id, see [StreamDeckType::recognize].
+#[derive(PartialEq, Debug)]
+pub enum Recognition {
+    /// A Streamdeck supported by this library.
+    Supported(StreamDeckType),
+    /// A Streamdeck that exists, but is not supported by this library (yet).
+    KnownUnsupported(&'static str),
+    /// Not a known Streamdeck.
+    Unknown,
+}
+
+/// Product ids (with the Elgato vendor id) and names of Streamdecks this library does not support.
+const KNOWN_UNSUPPORTED: [(u16, &str); 6] = [
+    (0x80, "Stream Deck MK.2"),
+    (0x86, "Stream Deck Pedal"),
+    (0x8f, "Stream Deck XL V2"),
+    (0x90, "Stream Deck Mini MK.2"),
+    (0xa5, "Stream Deck MK.2 (Scissor Keys)"),
+    (0xaa, "Stream Deck Studio"),
+];
+
+/// Vendor ids, product ids and types of devices registered with
+/// [StreamDeckType::register_compatible].
+static COMPATIBLE_DEVICES: Mutex<Vec<(u16, u16, StreamDeckType)>> = Mutex::new(Vec::new());
+
 /// The implementation of the [StreamDeckType] provides
 /// functions to get information specific to the StreamDeck type.
 impl StreamDeckType {
     /// List of ALL possible types
-    pub const ALL: [StreamDeckType; 4] = [
+    pub const ALL: [StreamDeckType; 6] = [
         StreamDeckType::Xl,
         StreamDeckType::OrigV2,
         StreamDeckType::Orig,
         StreamDeckType::Mini,
+        StreamDeckType::Plus,
+        StreamDeckType::Neo,
     ];
 
     /// The name of the Streamdeck type, as human readable string (english).
@@ -55,6 +158,8 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => "Streamdeck (original v2)",
             StreamDeckType::Orig => "Streamdeck original",
             StreamDeckType::Mini => "Streamdeck Mini",
+            StreamDeckType::Plus => "Streamdeck Plus",
+            StreamDeckType::Neo => "Streamdeck Neo",
         }
     }
 
@@ -68,15 +173,73 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => (3, 5),
             StreamDeckType::Orig => (3, 5),
             StreamDeckType::Mini => (2, 3),
+            StreamDeckType::Plus => (2, 4),
+            StreamDeckType::Neo => (2, 4),
         }
     }
 
+    /// All elements the user can interact with on the streamdeck.
+    ///
+    /// In contrast to [StreamDeckType::num_buttons], this also contains the inputs
+    /// that are not part of the key grid, like encoders and touch screens.
+    pub fn input_elements(&self) -> Vec<InputElement> {
+        let (rows, columns) = self.num_buttons();
+        let mut result: Vec<InputElement> = (0..rows * columns)
+            .map(|index| InputElement::Key {
+                index,
+                row: index / columns,
+                column: index % columns,
+            })
+            .collect();
+        if *self == StreamDeckType::Plus {
+            result.push(InputElement::TouchScreen {
+                width: 800,
+                height: 100,
+            });
+            result.extend((0..4).map(|index| InputElement::Encoder {
+                index,
+                column: index,
+            }));
+        }
+        result
+    }
+
     /// The total number of buttons found on the streamdeck.
     pub fn total_num_buttons(&self) -> usize {
         let (x, y) = self.num_buttons();
         (x * y) as usize
     }
 
+    /// The row and column of a button in the key grid.
+    ///
+    /// Buttons are counted row by row, starting at the top left.
+    /// Returns `None` if the device does not have the button.
+    pub fn button_coordinates(&self, button_id: u32) -> Option<(u32, u32)> {
+        let (rows, columns) = self.num_buttons();
+        if button_id >= rows * columns {
+            return None;
+        }
+        Some((button_id / columns, button_id % columns))
+    }
+
+    /// Names of the buttons by their position, for devices with few buttons.
+    ///
+    /// Only the Mini has named buttons (TopLeft, TopCenter, TopRight, BottomLeft,
+    /// BottomCenter, BottomRight). For the larger devices the list is empty.
+    pub fn named_buttons(&self) -> Vec<(&'static str, u8)> {
+        match *self {
+            StreamDeckType::Mini => Vec::from([
+                ("TopLeft", 0),
+                ("TopCenter", 1),
+                ("TopRight", 2),
+                ("BottomLeft", 3),
+                ("BottomCenter", 4),
+                ("BottomRight", 5),
+            ]),
+            _ => Vec::new(),
+        }
+    }
+
     /// The image format used by the Streamdeck.
     pub fn button_image_format(&self) -> StreamDeckImageFormat {
         match *self {
@@ -84,6 +247,8 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => StreamDeckImageFormat::Jpeg,
             StreamDeckType::Orig => StreamDeckImageFormat::Bmp,
             StreamDeckType::Mini => StreamDeckImageFormat::Bmp,
+            StreamDeckType::Plus => StreamDeckImageFormat::Jpeg,
+            StreamDeckType::Neo => StreamDeckImageFormat::Jpeg,
         }
     }
 
@@ -96,26 +261,144 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => (72, 72),
             StreamDeckType::Orig => (72, 72),
             StreamDeckType::Mini => (80, 80),
+            StreamDeckType::Plus => (120, 120),
+            StreamDeckType::Neo => (96, 96),
+        }
+    }
+
+    /// The expected size of the image for the info screen of the Streamdeck Neo.
+    ///
+    /// Returns `None` for devices without an info screen.
+    pub fn neo_info_image_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            StreamDeckType::Neo => Some((248, 58)),
+            _ => None,
+        }
+    }
+
+    /// The size of the touchscreen strip of the Streamdeck Plus.
+    ///
+    /// Returns `None` for devices without a touchscreen.
+    pub fn touchscreen_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            StreamDeckType::Plus => Some((800, 100)),
+            _ => None,
+        }
+    }
+
+    /// Check if button images can be used for `other` without converting them.
+    ///
+    /// This is the case, if the button images have the same size and image format.
+    /// Otherwise images must be resized, or encoded images encoded again.
+    pub fn image_compatible_with(&self, other: &StreamDeckType) -> bool {
+        self.button_image_size() == other.button_image_size()
+            && self.button_image_format() == other.button_image_format()
+    }
+
+    /// Check if the bytes look like an image in the format of the button images.
+    ///
+    /// Only the magic bytes at the start are checked, not the whole image.
+    pub fn is_valid_encoded_image(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(self.button_image_format().magic_bytes())
+    }
+
+    /// The color type of the raw images for this Streamdeck, before encoding.
+    ///
+    /// Use this when creating image buffers manually. All devices supported by this
+    /// library expect [ColorType::Rgb8], without an alpha channel.
+    pub fn color_type(&self) -> ColorType {
+        match *self {
+            StreamDeckType::Xl
+            | StreamDeckType::OrigV2
+            | StreamDeckType::Orig
+            | StreamDeckType::Mini
+            | StreamDeckType::Plus
+            | StreamDeckType::Neo => ColorType::Rgb8,
+        }
+    }
+
+    /// The complete specification of button images for this Streamdeck.
+    pub fn image_spec(&self) -> ImageSpec {
+        ImageSpec {
+            format: self.button_image_format(),
+            color_type: self.color_type(),
+            size: self.button_image_size(),
+            transformation: self.button_image_transformation(),
+        }
+    }
+
+    /// Typical number of image packets needed to send a full size button image.
+    ///
+    /// BMP images have a fixed size (a 54 byte header and 3 bytes per pixel), so for
+    /// devices using BMP this is exact. The size of JPEG images depends on the content;
+    /// for them this is a heuristic, not an upper bound: the size is estimated as a 623
+    /// byte header plus 4 bits per pixel, which is about what an icon or a photo needs
+    /// at the quality used by this crate. Simple images (e.g. a single color) need fewer
+    /// packets, images with fine detail everywhere (e.g. noise) need several times more.
+    pub fn typical_packet_count(&self) -> usize {
+        let (width, height) = self.button_image_size();
+        let pixels = (width * height) as usize;
+        let encoded_size = match self.button_image_format() {
+            StreamDeckImageFormat::Bmp => 54 + pixels * 3,
+            StreamDeckImageFormat::Jpeg => 623 + pixels / 2,
+        };
+        encoded_size.div_ceil(self.max_payload_size())
+    }
+
+    /// Check if the buttons of the device have displays, so images can be set.
+    ///
+    /// All types supported by this library have displays. Input only devices, like the
+    /// Stream Deck Pedal, are not supported and not a [StreamDeckType].
+    pub fn has_displays(&self) -> bool {
+        match *self {
+            StreamDeckType::Xl => true,
+            StreamDeckType::OrigV2 => true,
+            StreamDeckType::Orig => true,
+            StreamDeckType::Mini => true,
+            StreamDeckType::Plus => true,
+            StreamDeckType::Neo => true,
+        }
+    }
+
+    /// All types, that have displays on their buttons (see [StreamDeckType::has_displays]).
+    pub fn drivable_with_images() -> Vec<StreamDeckType> {
+        StreamDeckType::ALL
+            .into_iter()
+            .filter(StreamDeckType::has_displays)
+            .collect()
+    }
+
+    /// All capabilities of this Streamdeck type in one struct.
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            device_type: self.clone(),
+            name: self.name(),
+            num_buttons: self.num_buttons(),
+            input_elements: self.input_elements(),
+            image_spec: self.image_spec(),
+            info_image_size: self.neo_info_image_size(),
         }
     }
 
     /// Get the product id.
     ///
     /// Get the product id for this Streamdeck device (to compare with the
-    /// product_id returned by [HidApi]).
+    /// product_id returned by [hidapi::HidApi]).
     pub fn get_product_id(&self) -> u16 {
         match *self {
             StreamDeckType::Xl => 0x6c,
             StreamDeckType::OrigV2 => 0x6d,
             StreamDeckType::Orig => 0x60,
             StreamDeckType::Mini => 0x63,
+            StreamDeckType::Plus => 0x84,
+            StreamDeckType::Neo => 0x9a,
         }
     }
 
     /// Get the vendor id.
     ///
     /// Get the vendor id for this Streamdeck device (to compare with the
-    /// vendor_id returned by [HidApi]).
+    /// vendor_id returned by [hidapi::HidApi]).
     pub fn get_vendor_id(&self) -> u16 {
         // For now its always the same
         0x0fd9
@@ -124,19 +407,131 @@ impl StreamDeckType {
     /// Get a type from vendor and product id.
     ///
     /// Returns the Streamdeck type from vendor and product id.
+    /// Devices registered with [StreamDeckType::register_compatible] are recognized as well.
     pub fn from_vendor_and_product_id(vendor_id: u16, product_id: u16) -> Option<StreamDeckType> {
-        for t in StreamDeckType::ALL {
-            if t.get_vendor_id() == vendor_id && t.get_product_id() == product_id {
-                return Some(t);
-            }
+        StreamDeckType::ALL
+            .into_iter()
+            .find(|t| t.get_vendor_id() == vendor_id && t.get_product_id() == product_id)
+            .or_else(|| {
+                COMPATIBLE_DEVICES
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .find(|(vid, pid, _)| *vid == vendor_id && *pid == product_id)
+                    .map(|(_, _, device_type)| device_type.clone())
+            })
+    }
+
+    /// Register a device, that is compatible with a Streamdeck but has other ids.
+    ///
+    /// Some rebadged or third-party devices use the protocol of a Streamdeck, but a
+    /// different vendor and product id. After registering, they are recognized as
+    /// `base_type` (e.g. by [StreamDeckType::from_vendor_and_product_id] and when listing
+    /// devices) and behave exactly like it. They are opened by their path, so their own
+    /// ids are used and not those of `base_type`. The registry is global to the process.
+    /// Registering the same ids again replaces the type. The ids of the Streamdecks
+    /// themselves can not be overridden.
+    ///
+    /// # Example
+    /// ```
+    /// use streamdeck_hid_rs::StreamDeckType;
+    ///
+    /// fn main() {
+    ///     StreamDeckType::register_compatible(0x1234, 0x0042, StreamDeckType::OrigV2);
+    ///     assert_eq!(
+    ///         StreamDeckType::from_vendor_and_product_id(0x1234, 0x0042),
+    ///         Some(StreamDeckType::OrigV2)
+    ///     );
+    /// }
+    /// ```
+    pub fn register_compatible(vendor_id: u16, product_id: u16, base_type: StreamDeckType) {
+        let mut compatible_devices = COMPATIBLE_DEVICES.lock().unwrap();
+        compatible_devices.retain(|(vid, pid, _)| *vid != vendor_id || *pid != product_id);
+        compatible_devices.push((vendor_id, product_id, base_type));
+    }
+
+    /// Remove the leading bytes (report id and header) from a report read from the device.
+    ///
+    /// The first byte of the result is the state of the first button. Reports too
+    /// short to contain any button state give an empty slice.
+    pub fn strip_report_id<'a>(&self, buf: &'a [u8]) -> &'a [u8] {
+        buf.get(self.button_read_offset()..).unwrap_or_default()
+    }
+
+    /// Recognize a device by vendor and product id.
+    ///
+    /// Unlike [StreamDeckType::from_vendor_and_product_id] this distinguishes between
+    /// Streamdecks not supported by this library and devices that are no Streamdecks.
+    pub fn recognize(vendor_id: u16, product_id: u16) -> Recognition {
+        if let Some(device_type) = StreamDeckType::from_vendor_and_product_id(vendor_id, product_id)
+        {
+            return Recognition::Supported(device_type);
+        }
+        if vendor_id != StreamDeckType::Xl.get_vendor_id() {
+            return Recognition::Unknown;
+        }
+        KNOWN_UNSUPPORTED
+            .iter()
+            .find(|(known_product_id, _)| *known_product_id == product_id)
+            .map_or(Recognition::Unknown, |(_, name)| {
+                Recognition::KnownUnsupported(name)
+            })
+    }
+
+    /// Length of the feature reports (e.g. for brightness and reset) of the device.
+    ///
+    /// Custom feature reports sent to the device must have this length.
+    pub fn feature_report_size(&self) -> usize {
+        match *self {
+            StreamDeckType::Xl => 32,
+            StreamDeckType::OrigV2 => 32,
+            StreamDeckType::Orig => 17,
+            StreamDeckType::Mini => 17,
+            StreamDeckType::Plus => 32,
+            StreamDeckType::Neo => 32,
+        }
+    }
+
+    /// The brightness used for the device type, when no brightness is given.
+    ///
+    /// This is 70 for all types, as there are no per-model recommendations to base
+    /// different values on.
+    pub fn default_brightness(&self) -> u8 {
+        match *self {
+            StreamDeckType::Xl => 70,
+            StreamDeckType::OrigV2 => 70,
+            StreamDeckType::Orig => 70,
+            StreamDeckType::Mini => 70,
+            StreamDeckType::Plus => 70,
+            StreamDeckType::Neo => 70,
         }
-        None
+    }
+
+    /// The feature report sent to the device to set its brightness.
+    ///
+    /// This exposes the exact bytes, for example for protocol tools.
+    pub fn brightness_command(&self, brightness: u8) -> Vec<u8> {
+        self.brightness_packet(brightness)
+    }
+
+    /// The feature report sent to the device to reset it.
+    ///
+    /// This exposes the exact bytes, for example for protocol tools.
+    pub fn reset_command(&self) -> &'static [u8] {
+        self.reset_packet()
+    }
+
+    /// The packet written to the device to reset the key stream.
+    ///
+    /// This exposes the exact bytes, for example for protocol tools.
+    pub fn reset_key_stream_command(&self) -> Vec<u8> {
+        self.reset_key_stream_packet()
     }
 
     /// Returns the byte packet to be used to set the brightness of the device.
     pub(crate) fn brightness_packet(&self, brightness: u8) -> Vec<u8> {
         match *self {
-            StreamDeckType::Xl => {
+            StreamDeckType::Xl | StreamDeckType::Plus | StreamDeckType::Neo => {
                 let mut cmd = vec![0u8; 32];
                 cmd[..3].copy_from_slice(&[0x03, 0x08, brightness]);
                 cmd
@@ -174,6 +569,8 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => &StreamDeckType::RESET_PACKET_32,
             StreamDeckType::Orig => &StreamDeckType::RESET_PACKET_17,
             StreamDeckType::Mini => &StreamDeckType::RESET_PACKET_17,
+            StreamDeckType::Plus => &StreamDeckType::RESET_PACKET_32,
+            StreamDeckType::Neo => &StreamDeckType::RESET_PACKET_32,
         }
     }
 
@@ -184,6 +581,40 @@ impl StreamDeckType {
         r
     }
 
+    /// The feature report to set the key press sensitivity, if the device supports it.
+    ///
+    /// None of the devices supported by this library exposes the sensitivity yet;
+    /// newer models doing so get their report here.
+    pub(crate) fn key_sensitivity_packet(&self, _level: u8) -> Option<Vec<u8>> {
+        match *self {
+            StreamDeckType::Xl => None,
+            StreamDeckType::OrigV2 => None,
+            StreamDeckType::Orig => None,
+            StreamDeckType::Mini => None,
+            StreamDeckType::Plus => None,
+            StreamDeckType::Neo => None,
+        }
+    }
+
+    /// Buffer to read the firmware version feature report into, with the report id set.
+    pub(crate) fn firmware_version_report(&self) -> Vec<u8> {
+        match *self {
+            StreamDeckType::Xl
+            | StreamDeckType::OrigV2
+            | StreamDeckType::Plus
+            | StreamDeckType::Neo => {
+                let mut r = vec![0; 32];
+                r[0] = 0x05;
+                r
+            }
+            StreamDeckType::Orig | StreamDeckType::Mini => {
+                let mut r = vec![0; 17];
+                r[0] = 0x04;
+                r
+            }
+        }
+    }
+
     /// How big is an button image package for this device?
     pub(crate) fn image_package_size(&self) -> usize {
         match *self {
@@ -191,9 +622,75 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => 1024,
             StreamDeckType::Orig => 8191,
             StreamDeckType::Mini => 8191,
+            StreamDeckType::Plus => 1024,
+            StreamDeckType::Neo => 1024,
+        }
+    }
+
+    /// The maximum number of packages (pages) for one button image.
+    ///
+    /// The page number in the package header is a single byte for the Orig and the Mini
+    /// (stored plus one, so 255 pages), and two bytes for the other devices.
+    pub fn max_image_pages(&self) -> u32 {
+        match *self {
+            StreamDeckType::Xl
+            | StreamDeckType::OrigV2
+            | StreamDeckType::Plus
+            | StreamDeckType::Neo => u16::MAX as u32 + 1,
+            StreamDeckType::Mini | StreamDeckType::Orig => u8::MAX as u32,
         }
     }
 
+    /// Check a sequence of button image packages before writing it to a device.
+    ///
+    /// Every package must have the package size of the device, and a header as created
+    /// for the device, with consecutive page numbers and the same button for all packages.
+    /// There must not be more packages than [StreamDeckType::max_image_pages], and the
+    /// button must exist on the device. The payload itself is not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * 'packets' - The packages, in the order they would be written.
+    pub fn validate_packets(&self, packets: &[Vec<u8>]) -> Result<(), Error> {
+        let mut btn_index = None;
+        for (packet_index, packet) in packets.iter().enumerate() {
+            let invalid = Error::InvalidPacket { packet_index };
+            if packet_index >= self.max_image_pages() as usize
+                || packet.len() != self.image_package_size()
+            {
+                return Err(invalid);
+            }
+            let (packet_btn_index, payload_size) = match *self {
+                StreamDeckType::Xl
+                | StreamDeckType::OrigV2
+                | StreamDeckType::Plus
+                | StreamDeckType::Neo => (
+                    Some(packet[2]),
+                    packet[4] as usize | (packet[5] as usize) << 8,
+                ),
+                // The button is stored plus one, so 0 is no button
+                StreamDeckType::Mini | StreamDeckType::Orig => {
+                    (packet[5].checked_sub(1), self.max_payload_size())
+                }
+            };
+            let packet_btn_index = match packet_btn_index {
+                Some(btn_index) if (btn_index as usize) < self.total_num_buttons() => btn_index,
+                _ => return Err(invalid),
+            };
+            if payload_size > self.max_payload_size()
+                || *btn_index.get_or_insert(packet_btn_index) != packet_btn_index
+            {
+                return Err(invalid);
+            }
+            let header =
+                self.image_package_header(payload_size, packet_btn_index, packet_index as u16);
+            if !packet.starts_with(&header) {
+                return Err(invalid);
+            }
+        }
+        Ok(())
+    }
+
     /// Header for image packages send to set images on buttons.
     pub(crate) fn image_package_header(
         &self,
@@ -202,7 +699,10 @@ impl StreamDeckType {
         page_number: u16,
     ) -> Vec<u8> {
         match *self {
-            StreamDeckType::Xl | StreamDeckType::OrigV2 => {
+            StreamDeckType::Xl
+            | StreamDeckType::OrigV2
+            | StreamDeckType::Plus
+            | StreamDeckType::Neo => {
                 let length = min(self.image_package_size(), bytes_remaining);
                 vec![
                     0x2,
@@ -227,7 +727,7 @@ impl StreamDeckType {
                     (page_number + 1) as u8,
                     0,
                     if page_number == 1 { 0x01 } else { 0x00 },
-                    (btn_index + 1) as u8,
+                    btn_index + 1,
                     0x0,
                     0x0,
                     0x0,
@@ -243,6 +743,13 @@ impl StreamDeckType {
         }
     }
 
+    /// Transformation to get an image in user orientation from an image as displayed on the device.
+    ///
+    /// This is the inverse of [ImageSpec::transformation].
+    pub fn read_back_transformation(&self) -> ImageTransformation {
+        self.button_image_transformation().inverse()
+    }
+
     /// Tansformation needed to display the image correctly
     pub(crate) fn button_image_transformation(&self) -> ImageTransformation {
         match *self {
@@ -250,9 +757,61 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => ImageTransformation::Rotate180,
             StreamDeckType::Orig => ImageTransformation::Rotate180,
             StreamDeckType::Mini => ImageTransformation::Rotate270,
+            StreamDeckType::Plus => ImageTransformation::None,
+            StreamDeckType::Neo => ImageTransformation::Rotate180,
         }
     }
 
+    /// Header for image packages send to set the image on the info screen of the Neo.
+    pub(crate) fn info_image_package_header(
+        &self,
+        payload_size: usize,
+        is_last: bool,
+        page_number: u16,
+    ) -> Vec<u8> {
+        vec![
+            0x02,
+            0x0b,
+            0x00,
+            if is_last { 0x01 } else { 0x00 },
+            (payload_size & 0xFF) as u8,
+            (payload_size >> 8) as u8,
+            (page_number & 0xFF) as u8,
+            (page_number >> 8) as u8,
+        ]
+    }
+
+    /// Header for image packages send to set a region of the touchscreen of the Plus.
+    ///
+    /// The region is given as (x, y, width, height).
+    pub(crate) fn touchscreen_image_package_header(
+        &self,
+        region: (u16, u16, u16, u16),
+        payload_size: usize,
+        is_last: bool,
+        page_number: u16,
+    ) -> Vec<u8> {
+        let (x, y, width, height) = region;
+        vec![
+            0x02,
+            0x0c,
+            (x & 0xFF) as u8,
+            (x >> 8) as u8,
+            (y & 0xFF) as u8,
+            (y >> 8) as u8,
+            (width & 0xFF) as u8,
+            (width >> 8) as u8,
+            (height & 0xFF) as u8,
+            (height >> 8) as u8,
+            if is_last { 0x01 } else { 0x00 },
+            (page_number & 0xFF) as u8,
+            (page_number >> 8) as u8,
+            (payload_size & 0xFF) as u8,
+            (payload_size >> 8) as u8,
+            0x00,
+        ]
+    }
+
     /// Maximum payload per packet for the device
     pub(crate) fn max_payload_size(&self) -> usize {
         match *self {
@@ -260,6 +819,8 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => self.image_package_size() - 8,
             StreamDeckType::Orig => 7803,
             StreamDeckType::Mini => 7803,
+            StreamDeckType::Plus => self.image_package_size() - 8,
+            StreamDeckType::Neo => self.image_package_size() - 8,
         }
     }
 
@@ -270,6 +831,8 @@ impl StreamDeckType {
             StreamDeckType::OrigV2 => 4,
             StreamDeckType::Orig => 1,
             StreamDeckType::Mini => 1,
+            StreamDeckType::Plus => 4,
+            StreamDeckType::Neo => 4,
         }
     }
 }
@@ -287,6 +850,8 @@ mod tests {
         assert!(StreamDeckType::OrigV2.name().contains("(original v2)"));
         assert!(StreamDeckType::Orig.name().contains("original"));
         assert!(StreamDeckType::Mini.name().contains("Mini"));
+        assert!(StreamDeckType::Plus.name().contains("Plus"));
+        assert!(StreamDeckType::Neo.name().contains("Neo"));
     }
 
     #[test]
@@ -295,6 +860,40 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.num_buttons(), (3, 5));
         assert_eq!(StreamDeckType::Orig.num_buttons(), (3, 5));
         assert_eq!(StreamDeckType::Mini.num_buttons(), (2, 3));
+        assert_eq!(StreamDeckType::Plus.num_buttons(), (2, 4));
+    }
+
+    #[test]
+    fn test_input_elements() {
+        let xl = StreamDeckType::Xl.input_elements();
+        assert_eq!(xl.len(), 32);
+        assert!(xl
+            .iter()
+            .all(|element| matches!(element, InputElement::Key { .. })));
+        assert_eq!(
+            xl[9],
+            InputElement::Key {
+                index: 9,
+                row: 1,
+                column: 1
+            }
+        );
+
+        let plus = StreamDeckType::Plus.input_elements();
+        let keys = plus
+            .iter()
+            .filter(|element| matches!(element, InputElement::Key { .. }))
+            .count();
+        let encoders = plus
+            .iter()
+            .filter(|element| matches!(element, InputElement::Encoder { .. }))
+            .count();
+        assert_eq!(keys, 8);
+        assert_eq!(encoders, 4);
+        assert!(plus.contains(&InputElement::TouchScreen {
+            width: 800,
+            height: 100
+        }));
     }
 
     #[test]
@@ -303,6 +902,38 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.total_num_buttons(), 15);
         assert_eq!(StreamDeckType::Orig.total_num_buttons(), 15);
         assert_eq!(StreamDeckType::Mini.total_num_buttons(), 6);
+        assert_eq!(StreamDeckType::Plus.total_num_buttons(), 8);
+    }
+
+    #[test]
+    fn test_button_coordinates() {
+        assert_eq!(StreamDeckType::Xl.button_coordinates(0), Some((0, 0)));
+        assert_eq!(StreamDeckType::Xl.button_coordinates(5), Some((0, 5)));
+        assert_eq!(StreamDeckType::Xl.button_coordinates(9), Some((1, 1)));
+        assert_eq!(StreamDeckType::Xl.button_coordinates(31), Some((3, 7)));
+        assert_eq!(StreamDeckType::Xl.button_coordinates(32), None);
+        assert_eq!(StreamDeckType::Mini.button_coordinates(4), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_drivable_with_images() {
+        let drivable = StreamDeckType::drivable_with_images();
+        assert!(drivable.contains(&StreamDeckType::Xl));
+        assert_eq!(drivable.len(), StreamDeckType::ALL.len());
+        // The Pedal is input only and not even a supported type
+        assert_eq!(
+            StreamDeckType::from_vendor_and_product_id(0x0fd9, 0x86),
+            None
+        );
+    }
+
+    #[test]
+    fn test_named_buttons() {
+        let mini = StreamDeckType::Mini.named_buttons();
+        assert_eq!(mini.len(), StreamDeckType::Mini.total_num_buttons());
+        assert!(mini.contains(&("TopLeft", 0)));
+        assert!(mini.contains(&("BottomRight", 5)));
+        assert!(StreamDeckType::Xl.named_buttons().is_empty());
     }
 
     #[test]
@@ -323,6 +954,10 @@ mod tests {
             StreamDeckType::Mini.button_image_format(),
             StreamDeckImageFormat::Bmp
         );
+        assert_eq!(
+            StreamDeckType::Plus.button_image_format(),
+            StreamDeckImageFormat::Jpeg
+        );
     }
 
     #[test]
@@ -331,6 +966,153 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.button_image_size(), (72, 72));
         assert_eq!(StreamDeckType::Orig.button_image_size(), (72, 72));
         assert_eq!(StreamDeckType::Mini.button_image_size(), (80, 80));
+        assert_eq!(StreamDeckType::Plus.button_image_size(), (120, 120));
+        assert_eq!(StreamDeckType::Neo.button_image_size(), (96, 96));
+    }
+
+    #[test]
+    fn test_neo_info_image_size() {
+        assert_eq!(StreamDeckType::Neo.neo_info_image_size(), Some((248, 58)));
+        for device_type in StreamDeckType::ALL {
+            if device_type != StreamDeckType::Neo {
+                assert_eq!(device_type.neo_info_image_size(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_touchscreen_size() {
+        assert_eq!(StreamDeckType::Plus.touchscreen_size(), Some((800, 100)));
+        assert_eq!(StreamDeckType::Neo.touchscreen_size(), None);
+        assert_eq!(StreamDeckType::Xl.touchscreen_size(), None);
+    }
+
+    #[test]
+    fn test_image_compatible_with() {
+        // Same size, but bmp and jpeg
+        assert!(!StreamDeckType::Orig.image_compatible_with(&StreamDeckType::OrigV2));
+        assert!(!StreamDeckType::Xl.image_compatible_with(&StreamDeckType::Mini));
+        assert!(StreamDeckType::Xl.image_compatible_with(&StreamDeckType::Xl));
+        for device_type in StreamDeckType::ALL {
+            assert!(device_type.image_compatible_with(&device_type));
+        }
+    }
+
+    #[test]
+    fn test_max_image_pages() {
+        assert_eq!(StreamDeckType::Orig.max_image_pages(), 255);
+        assert_eq!(StreamDeckType::Mini.max_image_pages(), 255);
+        assert_eq!(StreamDeckType::Xl.max_image_pages(), 65536);
+        assert_eq!(StreamDeckType::OrigV2.max_image_pages(), 65536);
+        for device_type in StreamDeckType::ALL {
+            // The last page number must still be encoded without wrapping
+            let last_page = (device_type.max_image_pages() - 1) as u16;
+            let header = device_type.image_package_header(700, 1, last_page);
+            match device_type {
+                StreamDeckType::Orig | StreamDeckType::Mini => assert_eq!(header[2], 255),
+                _ => assert_eq!(&header[6..8], &[0xFF, 0xFF]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_type() {
+        assert_eq!(StreamDeckType::Xl.color_type(), ColorType::Rgb8);
+        assert_eq!(StreamDeckType::OrigV2.color_type(), ColorType::Rgb8);
+        assert_eq!(StreamDeckType::Orig.color_type(), ColorType::Rgb8);
+        assert_eq!(StreamDeckType::Mini.color_type(), ColorType::Rgb8);
+        assert_eq!(StreamDeckType::Plus.color_type(), ColorType::Rgb8);
+        assert_eq!(StreamDeckType::Neo.color_type(), ColorType::Rgb8);
+    }
+
+    #[test]
+    fn test_magic_bytes() {
+        assert_eq!(StreamDeckImageFormat::Jpeg.magic_bytes(), &[0xFF, 0xD8]);
+        assert_eq!(StreamDeckImageFormat::Bmp.magic_bytes(), b"BM");
+    }
+
+    #[test]
+    fn test_is_valid_encoded_image() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        let bmp = [b'B', b'M', 0x36, 0x3C, 0x00, 0x00];
+        assert!(StreamDeckType::Xl.is_valid_encoded_image(&jpeg));
+        assert!(!StreamDeckType::Xl.is_valid_encoded_image(&bmp));
+        assert!(StreamDeckType::Mini.is_valid_encoded_image(&bmp));
+        assert!(!StreamDeckType::Mini.is_valid_encoded_image(&jpeg));
+        assert!(!StreamDeckType::OrigV2.is_valid_encoded_image(&[]));
+    }
+
+    #[test]
+    fn test_image_spec() {
+        assert_eq!(
+            StreamDeckType::Xl.image_spec(),
+            ImageSpec {
+                format: StreamDeckImageFormat::Jpeg,
+                color_type: ColorType::Rgb8,
+                size: (96, 96),
+                transformation: ImageTransformation::Rotate180,
+            }
+        );
+        assert_eq!(
+            StreamDeckType::OrigV2.image_spec(),
+            ImageSpec {
+                format: StreamDeckImageFormat::Jpeg,
+                color_type: ColorType::Rgb8,
+                size: (72, 72),
+                transformation: ImageTransformation::Rotate180,
+            }
+        );
+        assert_eq!(
+            StreamDeckType::Orig.image_spec(),
+            ImageSpec {
+                format: StreamDeckImageFormat::Bmp,
+                color_type: ColorType::Rgb8,
+                size: (72, 72),
+                transformation: ImageTransformation::Rotate180,
+            }
+        );
+        assert_eq!(
+            StreamDeckType::Mini.image_spec(),
+            ImageSpec {
+                format: StreamDeckImageFormat::Bmp,
+                color_type: ColorType::Rgb8,
+                size: (80, 80),
+                transformation: ImageTransformation::Rotate270,
+            }
+        );
+        assert_eq!(
+            StreamDeckType::Plus.image_spec(),
+            ImageSpec {
+                format: StreamDeckImageFormat::Jpeg,
+                color_type: ColorType::Rgb8,
+                size: (120, 120),
+                transformation: ImageTransformation::None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_typical_packet_count() {
+        assert_eq!(StreamDeckType::Orig.typical_packet_count(), 2);
+        assert_eq!(StreamDeckType::Mini.typical_packet_count(), 3);
+        assert_eq!(StreamDeckType::Xl.typical_packet_count(), 6);
+        assert_eq!(StreamDeckType::OrigV2.typical_packet_count(), 4);
+        assert_eq!(StreamDeckType::Plus.typical_packet_count(), 8);
+        assert_eq!(StreamDeckType::Neo.typical_packet_count(), 6);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = StreamDeckType::Neo.capabilities();
+        assert_eq!(capabilities.device_type, StreamDeckType::Neo);
+        assert_eq!(capabilities.name, StreamDeckType::Neo.name());
+        assert_eq!(capabilities.num_buttons, StreamDeckType::Neo.num_buttons());
+        assert_eq!(
+            capabilities.input_elements,
+            StreamDeckType::Neo.input_elements()
+        );
+        assert_eq!(capabilities.image_spec, StreamDeckType::Neo.image_spec());
+        assert_eq!(capabilities.info_image_size, Some((248, 58)));
     }
 
     #[test]
@@ -351,6 +1133,52 @@ mod tests {
             StreamDeckType::from_vendor_and_product_id(0x0fd9, 0x6c),
             Some(StreamDeckType::Xl)
         );
+        assert_eq!(
+            StreamDeckType::from_vendor_and_product_id(0x0fd9, 0x84),
+            Some(StreamDeckType::Plus)
+        );
+        assert_eq!(
+            StreamDeckType::from_vendor_and_product_id(0x0fd9, 0x9a),
+            Some(StreamDeckType::Neo)
+        );
+    }
+
+    #[test]
+    fn test_register_compatible() {
+        // Setup
+        let (vendor_id, product_id) = (0x1a2b, 0x0c0d);
+        assert_eq!(
+            StreamDeckType::from_vendor_and_product_id(vendor_id, product_id),
+            None
+        );
+
+        // Act
+        StreamDeckType::register_compatible(vendor_id, product_id, StreamDeckType::Xl);
+        StreamDeckType::register_compatible(vendor_id, product_id, StreamDeckType::Mini);
+        let device_type = StreamDeckType::from_vendor_and_product_id(vendor_id, product_id);
+
+        // Test
+        assert_eq!(device_type, Some(StreamDeckType::Mini));
+        assert_eq!(
+            StreamDeckType::recognize(vendor_id, product_id),
+            Recognition::Supported(StreamDeckType::Mini)
+        );
+        let device_type = device_type.unwrap();
+        assert_eq!(device_type.total_num_buttons(), 6);
+        assert_eq!(
+            device_type.button_image_format(),
+            StreamDeckImageFormat::Bmp
+        );
+        assert_eq!(
+            device_type.reset_packet(),
+            StreamDeckType::Mini.reset_packet()
+        );
+        // The Streamdecks themselves are still recognized
+        StreamDeckType::register_compatible(0x0fd9, 0x6c, StreamDeckType::Mini);
+        assert_eq!(
+            StreamDeckType::from_vendor_and_product_id(0x0fd9, 0x6c),
+            Some(StreamDeckType::Xl)
+        );
     }
 
     #[test]
@@ -367,6 +1195,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_recognize() {
+        for t in StreamDeckType::ALL {
+            assert_eq!(
+                StreamDeckType::recognize(t.get_vendor_id(), t.get_product_id()),
+                Recognition::Supported(t.clone())
+            );
+        }
+        assert_eq!(
+            StreamDeckType::recognize(0x0fd9, 0x86),
+            Recognition::KnownUnsupported("Stream Deck Pedal")
+        );
+        assert_eq!(
+            StreamDeckType::recognize(0x0fd9, 0xf334),
+            Recognition::Unknown
+        );
+        assert_eq!(
+            StreamDeckType::recognize(0xf334, 0x86),
+            Recognition::Unknown
+        );
+    }
+
+    #[test]
+    fn test_feature_report_size() {
+        assert_eq!(StreamDeckType::Xl.feature_report_size(), 32);
+        assert_eq!(StreamDeckType::OrigV2.feature_report_size(), 32);
+        assert_eq!(StreamDeckType::Orig.feature_report_size(), 17);
+        assert_eq!(StreamDeckType::Mini.feature_report_size(), 17);
+        assert_eq!(StreamDeckType::Plus.feature_report_size(), 32);
+        assert_eq!(StreamDeckType::Neo.feature_report_size(), 32);
+        for t in StreamDeckType::ALL {
+            assert_eq!(t.brightness_packet(50).len(), t.feature_report_size());
+            assert_eq!(t.reset_packet().len(), t.feature_report_size());
+        }
+    }
+
+    #[test]
+    fn test_default_brightness() {
+        for t in StreamDeckType::ALL {
+            assert_eq!(t.default_brightness(), 70);
+        }
+    }
+
     #[test]
     fn test_brightness_packet() {
         // We only test the brightness byte ... the rest is constants
@@ -374,6 +1245,27 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.brightness_packet(23)[2], 23);
         assert_eq!(StreamDeckType::Orig.brightness_packet(34)[5], 34);
         assert_eq!(StreamDeckType::Mini.brightness_packet(35)[5], 35);
+        assert_eq!(StreamDeckType::Plus.brightness_packet(36)[2], 36);
+    }
+
+    #[test]
+    fn test_brightness_command() {
+        assert_eq!(
+            StreamDeckType::Xl.brightness_command(50)[..3],
+            [0x03, 0x08, 50]
+        );
+        assert_eq!(
+            StreamDeckType::OrigV2.brightness_command(51)[..3],
+            [0x03, 0x08, 51]
+        );
+        assert_eq!(
+            StreamDeckType::Orig.brightness_command(52)[..6],
+            [0x05, 0x55, 0xaa, 0xd1, 0x01, 52]
+        );
+        assert_eq!(
+            StreamDeckType::Mini.brightness_command(53)[..6],
+            [0x05, 0x55, 0xaa, 0xd1, 0x01, 53]
+        );
     }
 
     #[test]
@@ -382,6 +1274,7 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.reset_packet()[0], 0x03);
         assert_eq!(StreamDeckType::Orig.reset_packet()[0], 0x0b);
         assert_eq!(StreamDeckType::Mini.reset_packet()[0], 0x0b);
+        assert_eq!(StreamDeckType::Plus.reset_packet()[0], 0x03);
     }
 
     #[test]
@@ -392,12 +1285,115 @@ mod tests {
         assert_eq!(StreamDeckType::Mini.reset_key_stream_packet()[0], 2);
     }
 
+    #[test]
+    fn test_reset_command() {
+        assert_eq!(StreamDeckType::Xl.reset_command()[..2], [0x03, 0x02]);
+        assert_eq!(StreamDeckType::OrigV2.reset_command()[..2], [0x03, 0x02]);
+        assert_eq!(StreamDeckType::Orig.reset_command()[..2], [0x0b, 0x63]);
+        assert_eq!(StreamDeckType::Mini.reset_command()[..2], [0x0b, 0x63]);
+        assert_eq!(StreamDeckType::Plus.reset_command()[..2], [0x03, 0x02]);
+        assert_eq!(StreamDeckType::Neo.reset_command()[..2], [0x03, 0x02]);
+    }
+
+    #[test]
+    fn test_reset_key_stream_command() {
+        for device_type in StreamDeckType::ALL {
+            let command = device_type.reset_key_stream_command();
+            assert_eq!(command[0], 0x02);
+            assert_eq!(command.len(), device_type.image_package_size());
+        }
+    }
+
+    #[test]
+    fn test_key_sensitivity_packet() {
+        for t in StreamDeckType::ALL {
+            assert_eq!(t.key_sensitivity_packet(5), None);
+        }
+    }
+
+    #[test]
+    fn test_firmware_version_report() {
+        assert_eq!(StreamDeckType::Xl.firmware_version_report()[0], 0x05);
+        assert_eq!(StreamDeckType::OrigV2.firmware_version_report()[0], 0x05);
+        assert_eq!(StreamDeckType::Orig.firmware_version_report()[0], 0x04);
+        assert_eq!(StreamDeckType::Mini.firmware_version_report()[0], 0x04);
+    }
+
     #[test]
     fn test_image_package_size() {
         assert_eq!(StreamDeckType::Xl.image_package_size(), 1024);
         assert_eq!(StreamDeckType::OrigV2.image_package_size(), 1024);
         assert_eq!(StreamDeckType::Orig.image_package_size(), 8191);
         assert_eq!(StreamDeckType::Mini.image_package_size(), 8191);
+        assert_eq!(StreamDeckType::Plus.image_package_size(), 1024);
+    }
+
+    #[test]
+    fn test_validate_packets() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let (width, height) = device_type.button_image_size();
+            let image = image::RgbImage::new(width, height);
+            let packets = crate::image::image_packages(device_type.clone(), &image, 2).unwrap();
+            let mut short_packet = packets.clone();
+            short_packet[0].pop();
+            let mut other_button = packets.clone();
+            other_button
+                .extend(crate::image::image_packages(device_type.clone(), &image, 3).unwrap());
+
+            // Act / Test
+            assert!(device_type.validate_packets(&packets).is_ok());
+            assert!(matches!(
+                device_type.validate_packets(&short_packet),
+                Err(Error::InvalidPacket { packet_index: 0 })
+            ));
+            assert!(matches!(
+                device_type.validate_packets(&other_button),
+                Err(Error::InvalidPacket { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_packets_invalid_button() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let image = device_type.test_pattern(crate::TestPattern::Gradient);
+            let packets = crate::image::image_packages(device_type.clone(), &image, 2).unwrap();
+            let button_byte = match device_type {
+                StreamDeckType::Mini | StreamDeckType::Orig => 5,
+                _ => 2,
+            };
+            let with_button_byte = |value: u8| -> Vec<Vec<u8>> {
+                packets
+                    .iter()
+                    .map(|packet| {
+                        let mut packet = packet.clone();
+                        packet[button_byte] = value;
+                        packet
+                    })
+                    .collect()
+            };
+
+            // Act
+            let zeroed = device_type.validate_packets(&with_button_byte(0));
+            let out_of_range = device_type.validate_packets(&with_button_byte(0xFF));
+
+            // Test
+            match device_type {
+                // 0 does not encode any button
+                StreamDeckType::Mini | StreamDeckType::Orig => assert!(matches!(
+                    zeroed,
+                    Err(Error::InvalidPacket { packet_index: 0 })
+                )),
+                // 0 is the first button
+                _ => assert!(zeroed.is_ok()),
+            }
+            assert!(matches!(
+                out_of_range,
+                Err(Error::InvalidPacket { packet_index: 0 })
+            ));
+        }
     }
 
     #[test]
@@ -413,11 +1409,11 @@ mod tests {
             );
             assert_eq!(
                 StreamDeckType::Orig.image_package_header(700, btn_index, 1)[5],
-                (btn_index + 1) as u8
+                btn_index + 1
             );
             assert_eq!(
                 StreamDeckType::Mini.image_package_header(700, btn_index, 1)[5],
-                (btn_index + 1) as u8
+                btn_index + 1
             );
         }
     }
@@ -463,6 +1459,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_info_image_package_header() {
+        assert_eq!(
+            StreamDeckType::Neo.info_image_package_header(0x123, true, 0x201),
+            vec![0x02, 0x0b, 0x00, 0x01, 0x23, 0x01, 0x01, 0x02]
+        );
+        assert_eq!(
+            StreamDeckType::Neo.info_image_package_header(1016, false, 0)[3],
+            0x00
+        );
+    }
+
     #[test]
     fn test_button_image_transformation() {
         assert_eq!(
@@ -481,6 +1489,46 @@ mod tests {
             StreamDeckType::Mini.button_image_transformation(),
             ImageTransformation::Rotate270
         );
+        assert_eq!(
+            StreamDeckType::Plus.button_image_transformation(),
+            ImageTransformation::None
+        );
+    }
+
+    #[test]
+    fn test_image_transformation_inverse() {
+        assert_eq!(
+            ImageTransformation::None.inverse(),
+            ImageTransformation::None
+        );
+        assert_eq!(
+            ImageTransformation::Rotate90.inverse(),
+            ImageTransformation::Rotate270
+        );
+        assert_eq!(
+            ImageTransformation::Rotate180.inverse(),
+            ImageTransformation::Rotate180
+        );
+        assert_eq!(
+            ImageTransformation::Rotate270.inverse(),
+            ImageTransformation::Rotate90
+        );
+    }
+
+    #[test]
+    fn test_read_back_transformation() {
+        assert_eq!(
+            StreamDeckType::Mini.read_back_transformation(),
+            ImageTransformation::Rotate90
+        );
+        assert_eq!(
+            StreamDeckType::Xl.read_back_transformation(),
+            ImageTransformation::Rotate180
+        );
+        assert_eq!(
+            StreamDeckType::Plus.read_back_transformation(),
+            ImageTransformation::None
+        );
     }
 
     #[test]
@@ -489,6 +1537,27 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.max_payload_size(), 1024 - 8);
         assert_eq!(StreamDeckType::Orig.max_payload_size(), 7803);
         assert_eq!(StreamDeckType::Mini.max_payload_size(), 7803);
+        assert_eq!(StreamDeckType::Plus.max_payload_size(), 1024 - 8);
+    }
+
+    #[test]
+    fn test_strip_report_id() {
+        // Reports as sent when the first button is pressed
+        let reports: [(StreamDeckType, &[u8]); 6] = [
+            (StreamDeckType::Xl, &[0x01, 0x00, 0x20, 0x00, 0x01, 0x00]),
+            (
+                StreamDeckType::OrigV2,
+                &[0x01, 0x00, 0x0f, 0x00, 0x01, 0x00],
+            ),
+            (StreamDeckType::Orig, &[0x01, 0x01, 0x00]),
+            (StreamDeckType::Mini, &[0x01, 0x01, 0x00]),
+            (StreamDeckType::Plus, &[0x01, 0x00, 0x08, 0x00, 0x01, 0x00]),
+            (StreamDeckType::Neo, &[0x01, 0x00, 0x0c, 0x00, 0x01, 0x00]),
+        ];
+        for (device_type, report) in reports {
+            assert_eq!(device_type.strip_report_id(report), &[0x01, 0x00]);
+        }
+        assert!(StreamDeckType::Xl.strip_report_id(&[0x01, 0x00]).is_empty());
     }
 
     #[test]
@@ -497,5 +1566,6 @@ mod tests {
         assert_eq!(StreamDeckType::OrigV2.button_read_offset(), 4);
         assert_eq!(StreamDeckType::Orig.button_read_offset(), 1);
         assert_eq!(StreamDeckType::Mini.button_read_offset(), 1);
+        assert_eq!(StreamDeckType::Plus.button_read_offset(), 4);
     }
 }
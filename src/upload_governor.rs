@@ -0,0 +1,107 @@
+//! Limit the number of image uploads running at the same time.
+//!
+//! Several Streamdecks connected to the same USB controller can overwhelm it, when
+//! they are all sent large images at the same time from different threads.
+
+use std::sync::{Condvar, Mutex};
+
+/// A counting semaphore, shared between devices to limit concurrent image uploads.
+///
+/// Pass the same governor to all devices with
+/// [StreamDeckDevice::set_upload_governor](crate::StreamDeckDevice::set_upload_governor).
+/// Every image upload then waits for a free permit before the first packet is written,
+/// and keeps it until the last packet has been written.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use streamdeck_hid_rs::{StreamDeckDevice, UploadGovernor};
+///
+/// fn main() {
+///     let hidapi = hidapi::HidApi::new().unwrap();
+///     # let hidapi = streamdeck_hid_rs::hid_api_traits::create_api_mock_for_examples();
+///     let governor = Arc::new(UploadGovernor::new(1));
+///     let mut devices = Vec::new();
+///     for (_, info) in StreamDeckDevice::list_devices(&hidapi) {
+///         let mut device = StreamDeckDevice::open(&hidapi, &info).unwrap();
+///         device.set_upload_governor(governor.clone());
+///         devices.push(device);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UploadGovernor {
+    permits: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A permit from an [UploadGovernor], that is given back when dropped.
+#[derive(Debug)]
+pub struct UploadPermit<'a> {
+    governor: &'a UploadGovernor,
+}
+
+impl UploadGovernor {
+    /// Create a governor allowing `permits` uploads at the same time.
+    ///
+    /// # Panics
+    ///
+    /// If `permits` is 0, as no upload could ever start.
+    pub fn new(permits: usize) -> UploadGovernor {
+        assert!(permits > 0, "an upload governor needs at least one permit");
+        UploadGovernor {
+            permits: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait until a permit is free and take it.
+    pub fn acquire(&self) -> UploadPermit<'_> {
+        let mut permits = self.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = self.released.wait(permits).unwrap();
+        }
+        *permits -= 1;
+        UploadPermit { governor: self }
+    }
+
+    /// The number of permits currently free.
+    pub fn available_permits(&self) -> usize {
+        *self.permits.lock().unwrap()
+    }
+}
+
+impl Drop for UploadPermit<'_> {
+    fn drop(&mut self) {
+        *self.governor.permits.lock().unwrap() += 1;
+        self.governor.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_and_release() {
+        // Setup
+        let governor = UploadGovernor::new(2);
+
+        // Act
+        let first = governor.acquire();
+        let second = governor.acquire();
+        let available_while_held = governor.available_permits();
+        drop(first);
+        drop(second);
+
+        // Test
+        assert_eq!(available_while_held, 0);
+        assert_eq!(governor.available_permits(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_permits() {
+        UploadGovernor::new(0);
+    }
+}